@@ -5,11 +5,12 @@ use gores_mapgen_rust::generator::Generator;
 use gores_mapgen_rust::random::Seed;
 
 fn main() {
-    let map = Generator::generate_map(
+    let (map, _) = Generator::generate_map(
         30_000,
         &Seed::from_u64(42),
         &GenerationConfig::default(),
         &MapConfig::default(),
+        &GenerationConfig::get_all_configs(),
     )
     .unwrap();
 
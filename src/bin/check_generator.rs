@@ -5,11 +5,17 @@ use gores_mapgen_rust::random::Seed;
 fn main() {
     for seed in 0..u64::max_value() {
         println!("generating {:?}", seed);
-        Generator::generate_map(
+        let result = Generator::generate_map(
             200_000,
             &Seed::from_u64(seed),
             &GenerationConfig::get_all_configs().get("insaneV2").unwrap(),
             &MapConfig::get_all_configs().get("hor_line").unwrap(),
+            &GenerationConfig::get_all_configs(),
         );
+
+        match result {
+            Ok((_, backtracks)) => println!("finished, backtracks={:?}", backtracks),
+            Err(error) => println!("failed: {:}", error),
+        }
     }
 }
@@ -1,12 +1,13 @@
 use clap::{crate_version, Parser};
 use core::net::{IpAddr, Ipv4Addr, SocketAddr};
 use gores_mapgen_rust::config::MapConfig;
+use gores_mapgen_rust::config_store::ConfigStore;
+use gores_mapgen_rust::layout::LayoutType;
 use gores_mapgen_rust::random::Seed;
 use gores_mapgen_rust::{config::GenerationConfig, generator::Generator};
 use itertools::Itertools;
 use log::{debug, error, info, warn};
 use simple_logger::SimpleLogger;
-use std::collections::HashMap;
 use std::panic;
 
 use regex::Regex;
@@ -25,7 +26,14 @@ enum Command {
         name = "list",
         about = "print a list of available map- & generation configs"
     )]
-    ListConfigs,
+    ListConfigs(ListArgs),
+}
+
+#[derive(Parser, Debug)]
+struct ListArgs {
+    /// additional directory with user configs in gen_configs/ and map_configs/
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
 }
 
 #[derive(Parser, Debug)]
@@ -50,6 +58,10 @@ struct BridgeArgs {
     /// how many times generation is retried
     #[arg(default_value_t = 10, long, short('r'))]
     generation_retries: usize,
+
+    /// additional directory with user configs in gen_configs/ and map_configs/
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
 }
 
 #[derive(Debug)]
@@ -111,15 +123,15 @@ struct ServerBridge {
     /// stores information about vote while its still pending
     pending_vote: Option<Vote>,
 
-    /// stores all available generation configs
-    gen_configs: HashMap<String, GenerationConfig>,
-
-    /// stores all available map configs
-    map_configs: HashMap<String, MapConfig>,
+    /// stores all available generation and map configs
+    configs: ConfigStore,
 
     /// selected map config
     current_map_config: MapConfig,
 
+    /// selected procedural layout, its waypoints are generated from the seed of each map
+    current_layout: Option<LayoutType>,
+
     /// stores start arguments
     args: BridgeArgs,
 }
@@ -129,9 +141,9 @@ impl ServerBridge {
         ServerBridge {
             econ: Econ::new(args.econ_port, args.telnet_buffer),
             pending_vote: None,
-            gen_configs: GenerationConfig::get_all_configs(),
-            map_configs: MapConfig::get_all_configs(),
+            configs: ConfigStore::new(args.config_dir.clone()),
             current_map_config: MapConfig::get_initial_config(),
+            current_layout: None,
             args,
         }
     }
@@ -141,6 +153,8 @@ impl ServerBridge {
             if let Some(data) = self.econ.read() {
                 debug!("[RECV DEBUG]: {:?}", data);
 
+                self.reload_configs();
+
                 if !self.econ.authed {
                     self.check_auth(&data);
                 } else {
@@ -150,6 +164,18 @@ impl ServerBridge {
         }
     }
 
+    /// reloads all configs if config files changed on disk
+    fn reload_configs(&mut self) {
+        if !self.configs.has_changes() {
+            return;
+        }
+
+        self.configs.reload();
+        if let Some(map_config) = self.configs.map_configs.get(&self.current_map_config.name) {
+            self.current_map_config = map_config.clone();
+        }
+    }
+
     /// checks whether the econ message regards votes
     pub fn check_vote(&mut self, data: &String) {
         // this regex detects all possible chat messages involving votes
@@ -236,6 +262,7 @@ impl ServerBridge {
 
                 // get config based on name
                 let gen_config = self
+                    .configs
                     .gen_configs
                     .get(config_name)
                     .expect("config does not exist!")
@@ -250,19 +277,22 @@ impl ServerBridge {
                     .nth(1)
                     .unwrap();
 
-                // get config based on name
-                let map_config = self
-                    .map_configs
-                    .get(config_name)
-                    .expect("config does not exist!")
-                    .clone();
+                // get config based on name, or select a procedural layout
+                match self.configs.map_configs.get(config_name) {
+                    Some(map_config) => {
+                        self.current_map_config = map_config.clone();
+                        self.current_layout = None;
+                    }
+                    None => {
+                        let layout =
+                            LayoutType::from_name(config_name).expect("config does not exist!");
+                        self.current_layout = Some(layout);
+                    }
+                };
 
                 info!("[GEN] changed layout to {config_name}");
                 self.econ
                     .rcon_say(format!("[GEN] changed layout to {config_name}"));
-
-                // overwrite current map config
-                self.current_map_config = map_config;
             }
         } else {
             warn!("[VOTE] Vote Success, but no pending vote! unhandled vote type?");
@@ -285,6 +315,12 @@ impl ServerBridge {
             .unwrap()
             .join("random_map.map");
 
+        // use the map seed for procedural layouts, so maps can be reproduced using their seed
+        if let Some(layout) = self.current_layout {
+            let (width, height) = layout.default_size();
+            self.current_map_config = layout.generate(width, height, seed);
+        }
+
         let info_txt = format!(
             "[GEN] Generating | seed={:?} | gen_cfg={:?} | map_cfg={:?}",
             &seed, &gen_config.name, &self.current_map_config.name
@@ -293,20 +329,30 @@ impl ServerBridge {
         self.econ.rcon_say(info_txt);
 
         let gen_status = panic::catch_unwind(|| {
-            Generator::generate_map(100_000, &seed, gen_config, &self.current_map_config)
+            Generator::generate_map(
+                100_000,
+                &seed,
+                gen_config,
+                &self.current_map_config,
+                &self.configs.gen_configs,
+            )
         });
 
         match gen_status {
             // map was generated successfully
-            Ok(Ok(map)) => {
-                info!("[GEN] Finished Map Generation!");
+            Ok(Ok((map, backtracks))) => {
+                info!(
+                    "[GEN] Finished Map Generation! | backtracks={:?}",
+                    backtracks
+                );
                 map.export(&map_path);
                 info!("[GEN] Map was exported");
                 self.econ.send_rcon_cmd("change_map random_map".to_string());
                 self.econ.send_rcon_cmd("reload".to_string());
-                self.econ.send_rcon_cmd("say [GEN] Done...".to_string());
+                self.econ
+                    .send_rcon_cmd(format!("say [GEN] Done... (backtracks={:})", backtracks));
             }
-            // map generation failed -> just retry
+            // map generation failed (also after backtracking, if enabled) -> retry with new seed
             Ok(Err(generation_error)) => {
                 warn!("[GEN] Generation Error: {:?}", generation_error);
                 self.econ
@@ -333,17 +379,13 @@ impl ServerBridge {
     }
 }
 
-fn print_configs() {
-    println!(
-        "GenerationConfig: {}",
-        GenerationConfig::get_all_configs()
-            .keys()
-            .into_iter()
-            .join(",")
-    );
+fn print_configs(list_args: ListArgs) {
+    let configs = ConfigStore::new(list_args.config_dir);
+    println!("GenerationConfig: {}", configs.gen_configs.keys().join(","));
+    println!("MapConfig: {}", configs.map_configs.keys().join(","));
     println!(
-        "MapConfig: {}",
-        MapConfig::get_all_configs().keys().into_iter().join(",")
+        "Layouts: {}",
+        LayoutType::ALL.iter().map(|layout| layout.name()).join(",")
     );
 }
 
@@ -354,6 +396,6 @@ fn main() {
             let mut bridge = ServerBridge::new(bridge_args);
             bridge.start();
         }
-        Command::ListConfigs => print_configs(),
+        Command::ListConfigs(list_args) => print_configs(list_args),
     }
 }
@@ -1,4 +1,5 @@
 use gores_mapgen_rust::config::MapConfig;
+use gores_mapgen_rust::config_store::ConfigStore;
 use gores_mapgen_rust::{
     config::GenerationConfig, editor::*, fps_control::*, kernel::*, position::*, rendering::*,
     walker::*,
@@ -77,8 +78,8 @@ struct State {
 }
 
 fn draw_thingy(walker: &CuteWalker, flag: bool) {
-    let offset: usize = walker.inner_kernel.size / 2; // offset of kernel wrt. position (top/left)
-    let root_pos = Position::new(walker.pos.x - offset, walker.pos.y - offset);
+    let (offset, _) = walker.inner_kernel.get_extends(&walker.anchor); // offset wrt. position (top/left)
+    let root_pos = Position::new(walker.pos.x - offset.0, walker.pos.y - offset.1);
     for ((x, y), kernel_active) in walker.inner_kernel.vector.indexed_iter() {
         if *kernel_active {
             draw_rectangle(
@@ -97,15 +98,14 @@ fn draw_thingy(walker: &CuteWalker, flag: bool) {
     let size = walker.inner_kernel.size;
     let radius = walker.inner_kernel.radius;
 
-    // very crappy hotfix to deal with different center whether size is even or not
-    let offset = match size % 2 == 0 {
-        true => 0.0,
-        false => 0.5,
-    };
+    // kernel center wrt. walker tile center, depends on anchor for even sizes
+    let (offset_x, offset_y) = walker.anchor.center_offset(size);
+    let center_x = walker.pos.x as f32 + 0.5 + offset_x;
+    let center_y = walker.pos.y as f32 + 0.5 + offset_y;
 
     draw_circle_lines(
-        (walker.pos.x) as f32 + offset,
-        (walker.pos.y) as f32 + offset,
+        center_x,
+        center_y,
         radius.sqrt(),
         0.05,
         match flag {
@@ -115,8 +115,8 @@ fn draw_thingy(walker: &CuteWalker, flag: bool) {
     );
 
     draw_circle_lines(
-        (walker.pos.x) as f32 + offset,
-        (walker.pos.y) as f32 + offset,
+        center_x,
+        center_y,
         radius,
         0.025,
         match flag {
@@ -128,7 +128,11 @@ fn draw_thingy(walker: &CuteWalker, flag: bool) {
 
 #[macroquad::main("kernel_test")]
 async fn main() {
-    let mut editor = Editor::new(GenerationConfig::default(), MapConfig::default());
+    let mut editor = Editor::new(
+        GenerationConfig::default(),
+        MapConfig::default(),
+        ConfigStore::new(None),
+    );
     let mut fps_ctrl = FPSControl::new().with_max_fps(60);
 
     let mut state = State {
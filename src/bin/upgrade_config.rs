@@ -0,0 +1,85 @@
+use clap::{crate_version, Parser};
+use gores_mapgen_rust::config::GenerationConfig;
+use gores_mapgen_rust::migration::{migrate_gen_config, CURRENT_VERSION};
+use serde_json::Value;
+use std::fs;
+
+#[derive(Parser, Debug)]
+#[command(name = "Config Upgrade")]
+#[command(version = crate_version!())]
+#[command(about = "Upgrade generation config files to the current version in place", long_about = None)]
+struct Args {
+    /// generation config files to upgrade
+    #[arg(required = true)]
+    files: Vec<String>,
+
+    /// only report the changes, don't write any files
+    #[arg(short, long, default_value_t = false)]
+    dry_run: bool,
+
+    /// don't keep a .bak copy of the original files
+    #[arg(long, default_value_t = false)]
+    no_backup: bool,
+}
+
+/// upgrades a single config file, returns whether the file had to be changed
+fn upgrade_file(path: &str, args: &Args) -> Result<bool, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let value = serde_json::from_str(&data).map_err(|e| e.to_string())?;
+    let migration = migrate_gen_config(value)?;
+
+    for warning in migration.warnings.iter() {
+        println!("{}: {}", path, warning);
+    }
+
+    if !migration.is_upgrade() && migration.warnings.is_empty() {
+        println!("{}: already up to date", path);
+        return Ok(false);
+    }
+
+    // presets extending another preset only contain some fields, so they are stored as is
+    // instead of being filled up with default values
+    let serialized = match migration.config.get("extends") {
+        Some(Value::String(_)) => {
+            serde_json::to_string_pretty(&migration.config).map_err(|e| e.to_string())?
+        }
+        _ => {
+            let config: GenerationConfig =
+                serde_json::from_value(migration.config).map_err(|e| e.to_string())?;
+            config.validate()?;
+            serde_json::to_string_pretty(&config).map_err(|e| e.to_string())?
+        }
+    };
+
+    println!(
+        "{}: {} -> {}",
+        path,
+        migration.from_version.name(),
+        CURRENT_VERSION
+    );
+
+    if !args.dry_run {
+        if !args.no_backup {
+            fs::copy(path, format!("{}.bak", path)).map_err(|e| e.to_string())?;
+        }
+        fs::write(path, serialized).map_err(|e| e.to_string())?;
+    }
+
+    Ok(true)
+}
+
+fn main() {
+    let args = Args::parse();
+    let mut failed = false;
+
+    for path in args.files.iter() {
+        if let Err(err) = upgrade_file(path, &args) {
+            println!("{}: failed to upgrade: {}", path, err);
+            failed = true;
+        }
+    }
+
+    if failed {
+        std::process::exit(1);
+    }
+}
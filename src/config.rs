@@ -1,9 +1,13 @@
+use crate::inheritance::{diff_json, merge_json, resolve_inheritance};
+use crate::migration::{find_unknown_fields, migrate_gen_config, CURRENT_VERSION};
 use crate::position::{Position, ShiftDirection};
 use crate::random::RandomDistConfig;
 use log::warn;
 use rust_embed::RustEmbed;
 use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
 use std::fs::File;
 use std::io::Write;
@@ -29,6 +33,28 @@ pub struct MapConfig {
 
     /// height of the map
     pub height: usize,
+
+    /// generation config overrides for the paths starting at specific waypoints
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub overrides: Vec<WaypointOverride>,
+}
+
+/// Overrides of the generation config for the path starting at a waypoint, e.g. to make the
+/// map harder towards the finish. The overrides stay active until the next waypoint with
+/// overrides. They only affect the walker, post processing always uses the maps generation
+/// config.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct WaypointOverride {
+    /// index of the waypoint at which the overrides start
+    pub waypoint: usize,
+
+    /// name of a generation config preset that replaces the maps generation config
+    #[serde(default)]
+    pub gen_config: Option<String>,
+
+    /// generation config fields that are overridden, e.g. {"momentum_prob": 0.5}
+    #[serde(default)]
+    pub fields: Map<String, Value>,
 }
 
 impl MapConfig {
@@ -38,13 +64,25 @@ impl MapConfig {
         for file_name in MapConfigStorage::iter() {
             let file = MapConfigStorage::get(&file_name).unwrap();
             let data = std::str::from_utf8(&file.data).unwrap();
-            let config: MapConfig = serde_json::from_str(data).unwrap();
-            configs.insert(config.name.clone(), config);
+            if let Some(config) = MapConfig::parse_preset(data, &file_name) {
+                configs.insert(config.name.clone(), config);
+            }
         }
 
         configs
     }
 
+    /// parses a map config preset, invalid presets are skipped with a warning
+    pub fn parse_preset(data: &str, file_name: &str) -> Option<MapConfig> {
+        match serde_json::from_str::<MapConfig>(data) {
+            Ok(config) => Some(config),
+            Err(e) => {
+                warn!("couldn't parse map config {}: {}", file_name, e);
+                None
+            }
+        }
+    }
+
     pub fn save(&self, path: &str) {
         let mut file = File::create(path).expect("failed to create config file");
         let serialized = serde_json::to_string_pretty(self).expect("failed to serialize config");
@@ -59,6 +97,356 @@ impl MapConfig {
         let config: MapConfig = serde_json::from_str(data).unwrap();
         config
     }
+
+    /// returns the first issue if the waypoints can't be used with the given GenerationConfig
+    pub fn validate(&self, gen_config: &GenerationConfig) -> Result<(), &'static str> {
+        match self.validate_all(gen_config).first() {
+            Some(issue) => Err(issue.message),
+            None => Ok(()),
+        }
+    }
+
+    /// Returns all issues that would result in a crash or a failed generation. Waypoints must
+    /// keep enough distance to the map border for the largest possible kernel and the rooms.
+    /// As the walker only approximately follows the waypoints, this doesn't guarantee that
+    /// generations succeed.
+    pub fn validate_all(&self, gen_config: &GenerationConfig) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        // 1. Check waypoint count, walker starts at first waypoint and finishes at the last
+        if self.waypoints.len() < 2 {
+            issues.push(ConfigIssue::new(
+                "waypoints",
+                "at least two waypoints (start and finish) are required",
+            ));
+        }
+
+        // 2. Check waypoint bounds
+        let kernel_extend = gen_config.max_kernel_size() / 2;
+        let start_room_extend =
+            gen_config.start_room.size + 1 + gen_config.spawn_protection as usize;
+        let finish_room_extend = gen_config.finish_room.size + 1;
+
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            let field = format!("waypoints[{}]", index);
+
+            if waypoint.x >= self.width || waypoint.y >= self.height {
+                issues.push(ConfigIssue::new(&field, "waypoint is outside of the map"));
+                continue;
+            }
+
+            let distance_to_border = [
+                waypoint.x,
+                waypoint.y,
+                self.width - waypoint.x - 1,
+                self.height - waypoint.y - 1,
+            ]
+            .into_iter()
+            .min()
+            .unwrap();
+
+            if distance_to_border < kernel_extend {
+                issues.push(ConfigIssue::new(
+                    &field,
+                    "waypoint is too close to the map border for the largest kernel",
+                ));
+            } else if index == 0 && distance_to_border < start_room_extend {
+                issues.push(ConfigIssue::new(
+                    &field,
+                    "first waypoint is too close to the map border for the start room",
+                ));
+            } else if index + 1 == self.waypoints.len() && distance_to_border < finish_room_extend {
+                issues.push(ConfigIssue::new(
+                    &field,
+                    "last waypoint is too close to the map border for the finish room",
+                ));
+            }
+        }
+
+        // 3. Check duplicate waypoints
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            if self.waypoints[..index].contains(waypoint) {
+                issues.push(ConfigIssue::new(
+                    &format!("waypoints[{}]", index),
+                    "waypoint is a duplicate of a previous waypoint",
+                ));
+            }
+        }
+
+        // 4. Check path length, start and finish room must not overlap
+        let path_length: f32 = self
+            .waypoints
+            .windows(2)
+            .map(|pair| (pair[0].distance_squared(&pair[1]) as f32).sqrt())
+            .sum();
+        let min_path_length = (start_room_extend + finish_room_extend) as f32;
+        if self.waypoints.len() >= 2 && path_length < min_path_length {
+            issues.push(ConfigIssue::new(
+                "waypoints",
+                "path is too short, start and finish room would overlap",
+            ));
+        }
+
+        // 5. Check waypoint overrides
+        for (index, waypoint_override) in self.overrides.iter().enumerate() {
+            let field = format!("overrides[{}]", index);
+
+            if waypoint_override.waypoint + 1 >= self.waypoints.len() {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.waypoint", field),
+                    "there is no path starting at this waypoint",
+                ));
+            }
+
+            if self.overrides[..index]
+                .iter()
+                .any(|other| other.waypoint == waypoint_override.waypoint)
+            {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.waypoint", field),
+                    "waypoint already has overrides",
+                ));
+            }
+
+            let mut unknown = Vec::new();
+            find_unknown_fields(
+                &Value::Object(waypoint_override.fields.clone()),
+                &serde_json::to_value(gen_config).expect("failed to serialize config"),
+                "",
+                &mut unknown,
+            );
+            for unknown_field in unknown {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.fields.{}", field, unknown_field),
+                    "unknown generation config field",
+                ));
+            }
+
+            // named generation configs are only known when generating, see resolve_overrides
+            if waypoint_override.gen_config.is_some() {
+                continue;
+            }
+
+            match gen_config.with_overrides(&waypoint_override.fields) {
+                Ok(config) => issues.extend(
+                    config
+                        .validate_all()
+                        .into_iter()
+                        .filter(|issue| {
+                            waypoint_override
+                                .fields
+                                .keys()
+                                .any(|key| issue.affects(key))
+                        })
+                        .map(|issue| ConfigIssue {
+                            field: format!("{}.fields.{}", field, issue.field),
+                            message: issue.message,
+                        }),
+                ),
+                Err(message) => {
+                    issues.push(ConfigIssue::new(&format!("{}.fields", field), message))
+                }
+            }
+        }
+
+        issues
+    }
+
+    /// Resolves the waypoint overrides to complete generation configs, sorted by the waypoint
+    /// they start at. Named generation configs are looked up in gen_configs.
+    pub fn resolve_overrides(
+        &self,
+        gen_config: &GenerationConfig,
+        gen_configs: &HashMap<String, GenerationConfig>,
+    ) -> Result<Vec<(usize, GenerationConfig)>, &'static str> {
+        let mut resolved = Vec::new();
+
+        for waypoint_override in self.overrides.iter() {
+            let base_config = match &waypoint_override.gen_config {
+                Some(name) => gen_configs
+                    .get(name)
+                    .ok_or("waypoint override uses an unknown generation config")?,
+                None => gen_config,
+            };
+
+            let config = base_config.with_overrides(&waypoint_override.fields)?;
+            config.validate()?;
+            resolved.push((waypoint_override.waypoint, config));
+        }
+
+        resolved.sort_by_key(|(waypoint, _)| *waypoint);
+        Ok(resolved)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum SteeringMode {
+    /// shifts are rated by the euclidean distance to the next waypoint
+    #[default]
+    Greedy,
+
+    /// shifts are rated using a flow field towards the next waypoint, which is perturbed by
+    /// noise and avoids already carved blocks (see FlowField)
+    FlowField,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum SelfAvoidanceStrategy {
+    /// walker retreats along its path and tries again from there, see
+    /// self_avoidance_max_retreats
+    #[default]
+    Retreat,
+
+    /// walker ignores its path and continues with the sampled shift
+    ForceThrough,
+
+    /// generation fails with an error
+    Abort,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Copy, Default)]
+pub enum SpawnLayout {
+    /// consecutive spawns centered on the spawn platform
+    #[default]
+    Line,
+
+    /// spawns evenly spread over the entire spawn platform
+    Spread,
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct RoomConfig {
+    /// distance from room center to room border
+    pub size: usize,
+
+    /// distance from room border to the ends of the platform
+    pub platform_margin: usize,
+}
+
+impl Default for RoomConfig {
+    fn default() -> RoomConfig {
+        RoomConfig {
+            size: 5,
+            platform_margin: 3,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct SkipConfig {
+    /// (min, max) length of skips, both inclusive
+    pub length_bounds: (usize, usize),
+
+    /// min squared distance between the ends of two skips
+    pub min_spacing_sqr: usize,
+
+    /// max number of skips per map, 0 for no limit
+    pub max_count: usize,
+
+    /// probability for generating a valid skip
+    pub prob: f32,
+
+    /// also detect 45 degree skips
+    pub enable_diagonal: bool,
+
+    /// also detect skips that turn by 90 degrees inside the wall
+    pub enable_l_shaped: bool,
+}
+
+impl Default for SkipConfig {
+    fn default() -> SkipConfig {
+        SkipConfig {
+            length_bounds: (4, 11),
+            min_spacing_sqr: 45,
+            max_count: 0,
+            prob: 1.0,
+            enable_diagonal: false,
+            enable_l_shaped: false,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+#[serde(default)]
+pub struct DecorationConfig {
+    /// rgb sky color at the top of the screen
+    pub sky_top_color: (u8, u8, u8),
+
+    /// rgb sky color at the bottom of the screen
+    pub sky_bottom_color: (u8, u8, u8),
+
+    /// distance by which the background tiles extend from walls into the empty area
+    pub background_margin: usize,
+
+    /// number of parallax background quads
+    pub quad_count: usize,
+
+    /// (min, max) size of background quads in blocks
+    pub quad_size_bounds: (usize, usize),
+
+    /// parallax of the background quads in percent, lower values appear further away
+    pub quad_parallax: i32,
+
+    /// how much background quads are darkened compared to the sky, within [0, 1]
+    pub quad_darkening: f32,
+}
+
+impl Default for DecorationConfig {
+    fn default() -> DecorationConfig {
+        DecorationConfig {
+            sky_top_color: (94, 132, 174),
+            sky_bottom_color: (173, 207, 232),
+            background_margin: 1,
+            quad_count: 40,
+            quad_size_bounds: (4, 12),
+            quad_parallax: 30,
+            quad_darkening: 0.3,
+        }
+    }
+}
+
+/// Curve of a numeric generation config field over the map progress, which is 0 at the start
+/// and 1 at the finish. Values between points are linearly interpolated.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone, Default)]
+pub struct CurveConfig {
+    /// path of the field, nested fields and list elements are separated by dots (e.g.
+    /// start_room.size or platform_distance_bounds.0)
+    pub field: String,
+
+    /// (progress, value) points with increasing progress
+    pub points: Vec<(f32, f32)>,
+}
+
+impl CurveConfig {
+    /// value of the curve at the given progress, constant before the first and after the last
+    /// point
+    pub fn evaluate(&self, progress: f32) -> f32 {
+        let next_index = self
+            .points
+            .iter()
+            .position(|(point_progress, _)| *point_progress > progress);
+
+        match next_index {
+            None => self.points.last().map_or(0.0, |(_, value)| *value),
+            Some(0) => self.points[0].1,
+            Some(index) => {
+                let (start_progress, start_value) = self.points[index - 1];
+                let (end_progress, end_value) = self.points[index];
+                let t = (progress - start_progress) / (end_progress - start_progress);
+                start_value + t * (end_value - start_value)
+            }
+        }
+    }
+}
+
+/// returns the value of a nested field of a serialized config, see CurveConfig::field
+fn get_field_mut<'a>(config: &'a mut Value, field: &str) -> Option<&'a mut Value> {
+    field.split('.').try_fold(config, |value, key| match value {
+        Value::Array(values) => key.parse::<usize>().ok().and_then(|i| values.get_mut(i)),
+        _ => value.get_mut(key),
+    })
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
@@ -73,6 +461,10 @@ pub struct GenerationConfig {
     /// stores the GenerationConfig version for future migration
     pub version: String,
 
+    /// Name of a parent preset. Only the fields of this preset that are present in its file
+    /// override the parent, nested objects are merged recursively.
+    pub extends: Option<String>,
+
     /// probability for mutating inner radius
     pub inner_rad_mut_prob: f32,
 
@@ -94,9 +486,70 @@ pub struct GenerationConfig {
     /// probability for doing the last shift direction again
     pub momentum_prob: f32,
 
+    /// how shifts towards the next waypoint are rated
+    pub steering: SteeringMode,
+
+    /// size of noise features in blocks that the flow field steers around
+    pub flow_field_noise_scale: f32,
+
+    /// additional cost of walking through noise features, larger values result in more
+    /// meandering paths
+    pub flow_field_noise_strength: f32,
+
+    /// additional cost of walking through already carved (empty) blocks
+    pub flow_field_carved_penalty: f32,
+
+    /// number of walker steps after which the flow field towards the same waypoint is
+    /// recomputed, so that blocks carved since then are avoided as well. 0 computes the flow
+    /// field only once per waypoint.
+    pub flow_field_update_interval: usize,
+
+    /// avoid shifts that bring the walker close to older parts of its path, which would
+    /// create shortcuts between waypoints
+    pub enable_self_avoidance: bool,
+
+    /// minimum distance in blocks between the outer kernel and older parts of the path
+    pub self_avoidance_distance: usize,
+
+    /// number of steps after which a part of the path is avoided, so the walker can still
+    /// walk straight and change direction
+    pub self_avoidance_min_age: usize,
+
+    /// what the walker does if all shifts come too close to older parts of the path
+    pub self_avoidance_strategy: SelfAvoidanceStrategy,
+
+    /// number of steps the walker moves back along its path when retreating
+    pub self_avoidance_retreat_steps: usize,
+
+    /// maximum number of retreats towards a single waypoint, afterwards the walker forces
+    /// through its path (e.g. if the waypoint can only be reached by crossing the path)
+    pub self_avoidance_max_retreats: usize,
+
+    /// if a walker step fails (e.g. kernel out of bounds), restore a previous checkpoint and
+    /// resample from there instead of failing the entire generation
+    pub enable_backtracking: bool,
+
+    /// number of walker steps between checkpoints
+    pub backtracking_interval: usize,
+
+    /// maximum number of backtracks during a generation, afterwards the generation fails
+    pub max_backtracks: usize,
+
     /// maximum distance from empty blocks to nearest non empty block
     pub max_distance: f32,
 
+    /// enable noise based obstacles in open areas
+    pub enable_noise_obstacles: bool,
+
+    /// rough fraction of eligible open area that is covered by noise obstacles
+    pub noise_obstacle_density: f32,
+
+    /// size of noise features in blocks, larger values result in larger obstacles
+    pub noise_obstacle_scale: f32,
+
+    /// min distance from walls to blocks that may be turned into noise obstacles
+    pub noise_obstacle_min_clearance: f32,
+
     /// min distance to next waypoint that is considered reached
     pub waypoint_reached_dist: usize,
 
@@ -109,11 +562,15 @@ pub struct GenerationConfig {
     /// probabilities for (kernel circularity, probability)
     pub circ_probs: RandomDistConfig<f32>,
 
-    /// (min, max) distance for skips
-    pub skip_length_bounds: (usize, usize),
+    /// skips through hookable walls, generated before freeze skips
+    pub skips: SkipConfig,
+
+    /// skips through freeze only walls
+    pub freeze_skips: SkipConfig,
 
-    /// min distance between skips
-    pub skip_min_spacing_sqr: usize,
+    /// maximum number of skips per 1000 walker steps, 0 for no limit. Applies to skips and
+    /// freeze skips separately, in addition to their max_count.
+    pub max_skip_density: f32,
 
     /// min unconnected freeze obstacle size
     pub min_freeze_size: usize,
@@ -126,6 +583,95 @@ pub struct GenerationConfig {
     pub pulse_corner_delay: usize,
     pub pulse_max_kernel_size: usize,
 
+    /// Enable smooth transitions of the inner kernel size towards newly sampled sizes. Sizes
+    /// sampled during an ongoing transition are ignored, so each target is reached before the
+    /// next transition starts.
+    pub enable_smooth_kernel: bool,
+
+    /// max change of the inner kernel size per smooth transition interval
+    pub smooth_kernel_max_change: usize,
+
+    /// number of walker steps per smooth transition interval
+    pub smooth_kernel_interval: usize,
+
+    /// room placed around the first waypoint
+    pub start_room: RoomConfig,
+
+    /// room placed at the final walker position
+    pub finish_room: RoomConfig,
+
+    /// number of spawns in the start room, 0 fills the entire spawn platform
+    pub spawn_count: usize,
+
+    /// arrangement of spawns on the spawn platform
+    pub spawn_layout: SpawnLayout,
+
+    /// surround the start room with a freeze line, only replacing hookable blocks
+    pub spawn_protection: bool,
+
+    /// place a decorative podium at the bottom of the finish room
+    pub finish_podium: bool,
+
+    /// enable placing prefabs along the walker path
+    pub enable_prefabs: bool,
+
+    /// probability for placing a prefab at each walker step
+    pub prefab_prob: f32,
+
+    /// min walker steps between two prefabs
+    pub prefab_min_distance: usize,
+
+    /// probabilities for (prefab name, probability)
+    pub prefab_probs: RandomDistConfig<String>,
+
+    /// split the path into disconnected sections which are connected by teleporters
+    pub enable_tele_sections: bool,
+
+    /// probability for teleporting to the next waypoint instead of walking there
+    pub tele_section_prob: f32,
+
+    /// rooms placed at both ends of a teleporter, the target room also sets a checkpoint
+    pub tele_room: RoomConfig,
+
+    /// turn freeze floors into traps teleporting back to the last checkpoint (or spawn)
+    pub enable_checkpoint_traps: bool,
+
+    /// min length of a freeze floor to be turned into a trap
+    pub checkpoint_trap_min_length: usize,
+
+    /// probability for turning a freeze floor into a trap
+    pub checkpoint_trap_prob: f32,
+
+    /// place speedups in the walker direction on long straight runs
+    pub enable_speedups: bool,
+
+    /// number of consecutive walker steps in the same direction per speedup
+    pub speedup_straight_steps: usize,
+
+    /// number of speedup rows placed behind the walker position
+    pub speedup_length: usize,
+
+    /// base speedup force, doubled for upwards and halved for downwards speedups
+    pub speedup_force: u8,
+
+    /// max speed up to which speedups accelerate, 0 for no limit
+    pub speedup_max_speed: u8,
+
+    /// convert some wall segments next to the path into unhookable walls
+    pub enable_unhookable: bool,
+
+    /// probability for each wall surface block to start an unhookable segment
+    pub unhookable_prob: f32,
+
+    /// (min, max) number of blocks per unhookable segment
+    pub unhookable_length_bounds: (usize, usize),
+
+    /// generate visual decoration (background tiles, parallax quads and sky) for the export
+    pub enable_decoration: bool,
+
+    /// visual decoration settings
+    pub decoration: DecorationConfig,
+
     /// number of initial walker steps to perform fading. Will fade from max to min kernel size.
     pub fade_steps: usize,
 
@@ -134,24 +680,530 @@ pub struct GenerationConfig {
 
     /// goal min kernel size for fading
     pub fade_min_size: usize,
+
+    /// Curves that replace numeric fields depending on the map progress, e.g. for an easy start
+    /// and a hard finish. They only affect the walker, post processing uses the fields as is.
+    pub curves: Vec<CurveConfig>,
+}
+
+/// Single problem found while validating a config. The field is given as path, e.g.
+/// "skips.length_bounds" or "circ_probs.values[2]".
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigIssue {
+    pub field: String,
+    pub message: &'static str,
+}
+
+impl ConfigIssue {
+    pub fn new(field: &str, message: &'static str) -> ConfigIssue {
+        ConfigIssue {
+            field: field.to_string(),
+            message,
+        }
+    }
+
+    /// returns whether the issue affects the given field or any of its children
+    pub fn affects(&self, field: &str) -> bool {
+        match self.field.strip_prefix(field) {
+            Some(rest) => rest.is_empty() || rest.starts_with('.') || rest.starts_with('['),
+            None => false,
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.field, self.message)
+    }
+}
+
+/// checks that a probability distribution can be sampled from
+fn validate_random_dist<T>(
+    dist: &RandomDistConfig<T>,
+    field: &str,
+    values_required: bool,
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let probs_field = format!("{}.probs", field);
+    let values_field = format!("{}.values", field);
+
+    if dist.probs.is_empty() {
+        issues.push(ConfigIssue::new(
+            &probs_field,
+            "probabilities must not be empty",
+        ));
+    } else if dist
+        .probs
+        .iter()
+        .any(|prob| !prob.is_finite() || *prob < 0.0)
+    {
+        issues.push(ConfigIssue::new(
+            &probs_field,
+            "probabilities must be finite and not negative",
+        ));
+    } else if dist.probs.iter().all(|prob| *prob == 0.0) {
+        issues.push(ConfigIssue::new(
+            &probs_field,
+            "probabilities must not all be zero",
+        ));
+    }
+
+    match &dist.values {
+        Some(values) if values.len() != dist.probs.len() => issues.push(ConfigIssue::new(
+            &values_field,
+            "values and probabilities must have the same length",
+        )),
+        None if values_required => {
+            issues.push(ConfigIssue::new(&values_field, "values are required"))
+        }
+        _ => {}
+    }
+}
+
+/// checks that a probability is within [0, 1]
+fn validate_prob(prob: f32, field: &str, issues: &mut Vec<ConfigIssue>) {
+    if !(0.0..=1.0).contains(&prob) {
+        issues.push(ConfigIssue::new(field, "probability must be within [0, 1]"));
+    }
 }
 
 impl GenerationConfig {
-    /// returns an error if the configuration would result in a crash
+    /// largest kernel size the walker can use, incl. fading and pulses
+    pub fn max_kernel_size(&self) -> usize {
+        let max_value =
+            |values: &Option<Vec<usize>>| values.iter().flatten().copied().max().unwrap_or(0);
+
+        let max_inner_size = max_value(&self.inner_size_probs.values);
+        let max_sampled_size = max_inner_size + max_value(&self.outer_margin_probs.values);
+        let max_pulse_size = match self.enable_pulse {
+            true => max_inner_size + 4,
+            false => 0,
+        };
+
+        max_sampled_size
+            .max(max_pulse_size)
+            .max(self.fade_max_size + 2)
+    }
+
+    /// returns a copy of the config with the given fields replaced, nested fields are merged
+    pub fn with_overrides(
+        &self,
+        fields: &Map<String, Value>,
+    ) -> Result<GenerationConfig, &'static str> {
+        if fields.is_empty() {
+            return Ok(self.clone());
+        }
+
+        let config = serde_json::to_value(self).expect("failed to serialize config");
+        let merged = merge_json(&config, &Value::Object(fields.clone()));
+        serde_json::from_value(merged).map_err(|_| "overridden fields have invalid values")
+    }
+
+    /// Returns a copy of the config with all curves evaluated at the given progress. The curves
+    /// of the returned config are removed, as they have already been applied.
+    pub fn at_progress(&self, progress: f32) -> Result<GenerationConfig, &'static str> {
+        let mut config = serde_json::to_value(self).expect("failed to serialize config");
+
+        for curve in self.curves.iter() {
+            let value = curve.evaluate(progress);
+            let field =
+                get_field_mut(&mut config, &curve.field).ok_or("curve field doesn't exist")?;
+            *field = match field {
+                Value::Number(number) if number.is_f64() => Value::from(value),
+                Value::Number(_) => Value::from(value.round() as i64),
+                _ => return Err("curve field must be numeric"),
+            };
+        }
+
+        config["curves"] = Value::Array(Vec::new());
+        serde_json::from_value(config).map_err(|_| "curve values are invalid for their fields")
+    }
+
+    /// returns the first issue if the configuration would result in a crash
     pub fn validate(&self) -> Result<(), &'static str> {
-        // 1. Check that there is no inner kernel size of 0
-        for inner_size in self.inner_size_probs.values.as_ref().unwrap().iter() {
-            if *inner_size == 0 {
-                return Err("Invalid Config! (inner_size = 0)");
+        match self.validate_all().first() {
+            Some(issue) => Err(issue.message),
+            None => Ok(()),
+        }
+    }
+
+    /// returns all issues that would result in a crash or a broken generation
+    pub fn validate_all(&self) -> Vec<ConfigIssue> {
+        let mut issues = Vec::new();
+
+        // 1. Check kernel and shift distributions
+        validate_random_dist(
+            &self.inner_size_probs,
+            "inner_size_probs",
+            true,
+            &mut issues,
+        );
+        validate_random_dist(
+            &self.outer_margin_probs,
+            "outer_margin_probs",
+            true,
+            &mut issues,
+        );
+        validate_random_dist(&self.circ_probs, "circ_probs", true, &mut issues);
+        validate_random_dist(&self.shift_weights, "shift_weights", false, &mut issues);
+        validate_random_dist(&self.prefab_probs, "prefab_probs", true, &mut issues);
+
+        if let Some(inner_sizes) = &self.inner_size_probs.values {
+            for (index, inner_size) in inner_sizes.iter().enumerate() {
+                if *inner_size == 0 {
+                    issues.push(ConfigIssue::new(
+                        &format!("inner_size_probs.values[{}]", index),
+                        "inner kernel size must be larger than zero",
+                    ));
+                }
+            }
+        }
+
+        if let Some(circularities) = &self.circ_probs.values {
+            for (index, circularity) in circularities.iter().enumerate() {
+                if !(0.0..=1.0).contains(circularity) {
+                    issues.push(ConfigIssue::new(
+                        &format!("circ_probs.values[{}]", index),
+                        "circularity must be within [0, 1]",
+                    ));
+                }
             }
         }
 
+        if self.shift_weights.probs.len() != 4 {
+            issues.push(ConfigIssue::new(
+                "shift_weights.probs",
+                "shift weights require exactly 4 probabilities",
+            ));
+        }
+
+        for (prob, field) in [
+            (self.inner_rad_mut_prob, "inner_rad_mut_prob"),
+            (self.inner_size_mut_prob, "inner_size_mut_prob"),
+            (self.outer_rad_mut_prob, "outer_rad_mut_prob"),
+            (self.outer_size_mut_prob, "outer_size_mut_prob"),
+            (self.momentum_prob, "momentum_prob"),
+        ] {
+            validate_prob(prob, field, &mut issues);
+        }
+
+        if self.platform_distance_bounds.0 > self.platform_distance_bounds.1 {
+            issues.push(ConfigIssue::new(
+                "platform_distance_bounds",
+                "platform distance bounds must satisfy min <= max",
+            ));
+        }
+
         // 2. Check fade config
-        if self.fade_max_size == 0 || self.fade_min_size == 0 {
-            return Err("fade kernel sizes must be larger than zero");
+        for (size, field) in [
+            (self.fade_max_size, "fade_max_size"),
+            (self.fade_min_size, "fade_min_size"),
+        ] {
+            if size == 0 {
+                issues.push(ConfigIssue::new(
+                    field,
+                    "fade kernel sizes must be larger than zero",
+                ));
+            }
+        }
+
+        // 3. Check room configs
+        for (room, field) in [
+            (&self.start_room, "start_room"),
+            (&self.finish_room, "finish_room"),
+            (&self.tele_room, "tele_room"),
+        ] {
+            if room.size < 2 {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.size", field),
+                    "room size must be at least 2",
+                ));
+            }
+            if room.platform_margin > room.size {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.platform_margin", field),
+                    "room platform margin must not exceed room size",
+                ));
+            }
+        }
+
+        let spawn_platform_width = 2 * self
+            .start_room
+            .size
+            .saturating_sub(self.start_room.platform_margin)
+            + 1;
+        if self.spawn_count > spawn_platform_width {
+            issues.push(ConfigIssue::new(
+                "spawn_count",
+                "spawn count exceeds spawn platform width",
+            ));
+        }
+
+        if self.finish_podium && self.finish_room.size < 3 {
+            issues.push(ConfigIssue::new(
+                "finish_room.size",
+                "finish podium requires a finish room size of at least 3",
+            ));
+        }
+
+        // 4. Check noise obstacle config
+        if self.enable_noise_obstacles && self.noise_obstacle_scale <= 0.0 {
+            issues.push(ConfigIssue::new(
+                "noise_obstacle_scale",
+                "noise obstacle scale must be larger than zero",
+            ));
+        }
+
+        // 5. Check smooth kernel config
+        if self.enable_smooth_kernel {
+            if self.smooth_kernel_max_change == 0 {
+                issues.push(ConfigIssue::new(
+                    "smooth_kernel_max_change",
+                    "smooth kernel max change must be larger than zero",
+                ));
+            }
+            if self.smooth_kernel_interval == 0 {
+                issues.push(ConfigIssue::new(
+                    "smooth_kernel_interval",
+                    "smooth kernel interval must be larger than zero",
+                ));
+            }
+        }
+
+        // 6. Check skip configs
+        for (skip_config, field) in [(&self.skips, "skips"), (&self.freeze_skips, "freeze_skips")] {
+            if skip_config.length_bounds.0 > skip_config.length_bounds.1 {
+                issues.push(ConfigIssue::new(
+                    &format!("{}.length_bounds", field),
+                    "skip length bounds must satisfy min <= max",
+                ));
+            }
+            validate_prob(skip_config.prob, &format!("{}.prob", field), &mut issues);
+        }
+
+        if self.max_skip_density < 0.0 {
+            issues.push(ConfigIssue::new(
+                "max_skip_density",
+                "max skip density must not be negative",
+            ));
+        }
+
+        // 7. Check prefab, tele section and checkpoint trap config
+        if self.enable_prefabs {
+            validate_prob(self.prefab_prob, "prefab_prob", &mut issues);
         }
 
-        Ok(())
+        if self.enable_tele_sections {
+            validate_prob(self.tele_section_prob, "tele_section_prob", &mut issues);
+            if self.tele_room.size < 5 {
+                issues.push(ConfigIssue::new(
+                    "tele_room.size",
+                    "tele sections require a tele room size of at least 5",
+                ));
+            }
+        }
+
+        if self.enable_checkpoint_traps {
+            validate_prob(
+                self.checkpoint_trap_prob,
+                "checkpoint_trap_prob",
+                &mut issues,
+            );
+            if self.checkpoint_trap_min_length == 0 {
+                issues.push(ConfigIssue::new(
+                    "checkpoint_trap_min_length",
+                    "checkpoint trap min length must be larger than zero",
+                ));
+            }
+        }
+
+        // 8. Check speedup config
+        if self.enable_speedups {
+            if self.speedup_straight_steps == 0 {
+                issues.push(ConfigIssue::new(
+                    "speedup_straight_steps",
+                    "speedup straight steps must be larger than zero",
+                ));
+            }
+            if self.speedup_length == 0 {
+                issues.push(ConfigIssue::new(
+                    "speedup_length",
+                    "speedup length must be larger than zero",
+                ));
+            }
+        }
+
+        // 9. Check unhookable config
+        if self.enable_unhookable {
+            validate_prob(self.unhookable_prob, "unhookable_prob", &mut issues);
+            if self.unhookable_length_bounds.0 == 0
+                || self.unhookable_length_bounds.0 > self.unhookable_length_bounds.1
+            {
+                issues.push(ConfigIssue::new(
+                    "unhookable_length_bounds",
+                    "unhookable length bounds must satisfy 0 < min <= max",
+                ));
+            }
+        }
+
+        // 10. Check decoration config
+        if self.enable_decoration {
+            let decoration = &self.decoration;
+            if decoration.quad_size_bounds.0 == 0
+                || decoration.quad_size_bounds.0 > decoration.quad_size_bounds.1
+            {
+                issues.push(ConfigIssue::new(
+                    "decoration.quad_size_bounds",
+                    "decoration quad size bounds must satisfy 0 < min <= max",
+                ));
+            }
+            validate_prob(
+                decoration.quad_darkening,
+                "decoration.quad_darkening",
+                &mut issues,
+            );
+        }
+
+        // 11. Check flow field config
+        if self.steering == SteeringMode::FlowField {
+            if self.flow_field_noise_scale <= 0.0 {
+                issues.push(ConfigIssue::new(
+                    "flow_field_noise_scale",
+                    "flow field noise scale must be larger than zero",
+                ));
+            }
+            if self.flow_field_noise_strength < 0.0 {
+                issues.push(ConfigIssue::new(
+                    "flow_field_noise_strength",
+                    "flow field noise strength must not be negative",
+                ));
+            }
+            if self.flow_field_carved_penalty < 0.0 {
+                issues.push(ConfigIssue::new(
+                    "flow_field_carved_penalty",
+                    "flow field carved penalty must not be negative",
+                ));
+            }
+        }
+
+        // 12. Check self avoidance config
+        if self.enable_self_avoidance {
+            if self.self_avoidance_min_age <= self.self_avoidance_distance {
+                issues.push(ConfigIssue::new(
+                    "self_avoidance_min_age",
+                    "self avoidance min age must be larger than the self avoidance distance",
+                ));
+            }
+            if self.self_avoidance_strategy == SelfAvoidanceStrategy::Retreat
+                && self.self_avoidance_retreat_steps == 0
+            {
+                issues.push(ConfigIssue::new(
+                    "self_avoidance_retreat_steps",
+                    "self avoidance retreat steps must be larger than zero",
+                ));
+            }
+        }
+
+        // 13. Check backtracking config
+        if self.enable_backtracking && self.backtracking_interval == 0 {
+            issues.push(ConfigIssue::new(
+                "backtracking_interval",
+                "backtracking interval must be larger than zero",
+            ));
+        }
+
+        // 14. Check curves
+        if !self.curves.is_empty() {
+            let mut config = serde_json::to_value(self).expect("failed to serialize config");
+            let mut curves_valid = true;
+
+            for (index, curve) in self.curves.iter().enumerate() {
+                let field = format!("curves[{}]", index);
+
+                if !matches!(
+                    get_field_mut(&mut config, &curve.field),
+                    Some(Value::Number(_))
+                ) {
+                    issues.push(ConfigIssue::new(
+                        &format!("{}.field", field),
+                        "curve field must be an existing numeric field",
+                    ));
+                    curves_valid = false;
+                } else if self.curves[..index]
+                    .iter()
+                    .any(|other| other.field == curve.field)
+                {
+                    issues.push(ConfigIssue::new(
+                        &format!("{}.field", field),
+                        "field already has a curve",
+                    ));
+                }
+
+                if curve.points.is_empty() {
+                    issues.push(ConfigIssue::new(
+                        &format!("{}.points", field),
+                        "curve needs at least one point",
+                    ));
+                    curves_valid = false;
+                }
+
+                let progress_valid = curve
+                    .points
+                    .iter()
+                    .all(|(progress, _)| (0.0..=1.0).contains(progress))
+                    && curve.points.windows(2).all(|pair| pair[0].0 < pair[1].0);
+                if !progress_valid {
+                    issues.push(ConfigIssue::new(
+                        &format!("{}.points", field),
+                        "curve progress must be increasing and within [0, 1]",
+                    ));
+                    curves_valid = false;
+                }
+            }
+
+            // values between points can't exceed the points, so checking the points is enough
+            let mut progress_values: Vec<f32> = self
+                .curves
+                .iter()
+                .flat_map(|curve| curve.points.iter().map(|(progress, _)| *progress))
+                .collect();
+            progress_values.sort_by(f32::total_cmp);
+            progress_values.dedup();
+
+            for progress in progress_values.into_iter().filter(|_| curves_valid) {
+                let curve_issues = match self.at_progress(progress) {
+                    Ok(config) => config.validate_all(),
+                    Err(message) => vec![ConfigIssue::new("curves", message)],
+                };
+
+                for issue in curve_issues {
+                    // issues of the config itself have already been reported
+                    if issues.contains(&issue) {
+                        continue;
+                    }
+
+                    let index = self.curves.iter().position(|curve| {
+                        issue.affects(&curve.field)
+                            || curve
+                                .field
+                                .strip_prefix(&issue.field)
+                                .is_some_and(|rest| rest.starts_with('.'))
+                    });
+                    let issue = ConfigIssue {
+                        field: match index {
+                            Some(index) => format!("curves[{}].points", index),
+                            None => "curves".to_string(),
+                        },
+                        ..issue
+                    };
+                    if !issues.contains(&issue) {
+                        issues.push(issue);
+                    }
+                }
+            }
+        }
+
+        issues
     }
 
     pub fn save(&self, path: &str) {
@@ -161,27 +1213,119 @@ impl GenerationConfig {
             .expect("failed to write to config file");
     }
 
+    /// saves only the fields that differ from the parent preset, see extends
+    pub fn save_diff(&self, path: &str, parent: &GenerationConfig) {
+        // serialize via string, so f32 values are not widened to f64 (2.3 -> 2.299999952316284)
+        let to_value = |config: &GenerationConfig| -> Value {
+            let serialized = serde_json::to_string(config).expect("failed to serialize config");
+            serde_json::from_str(&serialized).unwrap()
+        };
+        let config = to_value(self);
+        let parent = to_value(parent);
+        let mut diff = diff_json(&parent, &config).unwrap_or(Value::Object(Default::default()));
+
+        // identifying fields are always stored, even if they match the parent
+        for key in ["name", "version", "extends"] {
+            diff.as_object_mut()
+                .unwrap()
+                .insert(key.to_string(), config[key].clone());
+        }
+
+        let mut file = File::create(path).expect("failed to create config file");
+        let serialized = serde_json::to_string_pretty(&diff).expect("failed to serialize config");
+        file.write_all(serialized.as_bytes())
+            .expect("failed to write to config file");
+    }
+
+    /// parses a serialized config of any known version into its json representation. Older
+    /// versions are migrated to the current version and all migration warnings are logged.
+    pub fn migrate_json(data: &str, name: &str) -> Result<Value, String> {
+        let value: Value = serde_json::from_str(data).map_err(|e| e.to_string())?;
+        let migration = migrate_gen_config(value)?;
+
+        if migration.is_upgrade() {
+            warn!(
+                "gen config {} migrated from version {}",
+                name,
+                migration.from_version.name()
+            );
+        }
+        for warning in migration.warnings.iter() {
+            warn!("gen config {}: {}", name, warning);
+        }
+
+        Ok(migration.config)
+    }
+
+    /// parses a serialized config of any known version, see migrate_json
+    pub fn from_json(data: &str, name: &str) -> Result<GenerationConfig, String> {
+        let value = GenerationConfig::migrate_json(data, name)?;
+        serde_json::from_value(value).map_err(|e| e.to_string())
+    }
+
     pub fn load(path: &str) -> GenerationConfig {
         let serialized_from_file = fs::read_to_string(path).expect("failed to read config file");
-        let deserialized: GenerationConfig =
-            serde_json::from_str(&serialized_from_file).expect("failed to deserialize config file");
-
-        deserialized
+        GenerationConfig::from_json(&serialized_from_file, path)
+            .expect("failed to deserialize config file")
     }
 
-    pub fn get_all_configs() -> HashMap<String, GenerationConfig> {
-        let mut configs = HashMap::new();
+    /// returns the file name and migrated json representation of all embedded presets
+    pub fn get_embedded_presets() -> Vec<(String, Value)> {
+        let mut presets = Vec::new();
 
         for file_name in GenerationConfigStorage::iter() {
             let file = GenerationConfigStorage::get(&file_name).unwrap();
             let data = std::str::from_utf8(&file.data).unwrap();
-            match serde_json::from_str::<GenerationConfig>(data) {
-                Ok(config) => {
-                    configs.insert(config.name.clone(), config);
+            match GenerationConfig::migrate_json(data, &file_name) {
+                Ok(value) => presets.push((file_name.to_string(), value)),
+                Err(e) => warn!("couldn't parse gen config {}: {}", file_name, e),
+            }
+        }
+
+        presets
+    }
+
+    pub fn get_all_configs() -> HashMap<String, GenerationConfig> {
+        GenerationConfig::resolve_presets(GenerationConfig::get_embedded_presets())
+    }
+
+    /// Resolves the inheritance of presets given by file name and json representation, see
+    /// extends. Presets override earlier presets with the same name. Presets that can't be
+    /// resolved or are invalid are skipped with a warning.
+    pub fn resolve_presets(presets: Vec<(String, Value)>) -> HashMap<String, GenerationConfig> {
+        let mut file_names = HashMap::new();
+        let mut values = HashMap::new();
+        for (file_name, value) in presets {
+            match value.get("name").and_then(Value::as_str) {
+                Some(name) => {
+                    file_names.insert(name.to_string(), file_name);
+                    values.insert(name.to_string(), value);
                 }
+                None => warn!("gen config {} has no name", file_name),
+            }
+        }
+
+        let mut configs = HashMap::new();
+        for (name, result) in resolve_inheritance(&values) {
+            let file_name = file_names.get(&name).unwrap();
+            let config = result.and_then(|value| {
+                serde_json::from_value::<GenerationConfig>(value).map_err(|e| e.to_string())
+            });
+
+            let config = match config {
+                Ok(config) => config,
                 Err(e) => {
-                    warn!("couldn't parse gen config {}: {}", file_name, e);
+                    warn!("couldn't load gen config {}: {}", file_name, e);
+                    continue;
                 }
+            };
+
+            let issues = config.validate_all();
+            for issue in issues.iter() {
+                warn!("invalid gen config {}: {}", file_name, issue);
+            }
+            if issues.is_empty() {
+                configs.insert(name, config);
             }
         }
 
@@ -195,8 +1339,7 @@ impl GenerationConfig {
         } else {
             let file = GenerationConfigStorage::get("hardV2.json").unwrap();
             let data = std::str::from_utf8(&file.data).unwrap();
-            let config: GenerationConfig = serde_json::from_str(data).unwrap();
-            config
+            GenerationConfig::from_json(data, "hardV2.json").unwrap()
         }
     }
 }
@@ -208,7 +1351,8 @@ impl Default for GenerationConfig {
         GenerationConfig {
             name: "default".to_string(),
             description: None,
-            version: "1.0".to_string(),
+            version: CURRENT_VERSION.to_string(),
+            extends: None,
             inner_rad_mut_prob: 0.25,
             inner_size_mut_prob: 0.5,
             outer_rad_mut_prob: 0.25,
@@ -216,21 +1360,86 @@ impl Default for GenerationConfig {
             shift_weights: RandomDistConfig::new(None, vec![0.4, 0.22, 0.2, 0.18]),
             platform_distance_bounds: (500, 750),
             momentum_prob: 0.01,
+            steering: SteeringMode::Greedy,
+            flow_field_noise_scale: 20.0,
+            flow_field_noise_strength: 5.0,
+            flow_field_carved_penalty: 30.0,
+            flow_field_update_interval: 1000,
+            enable_self_avoidance: false,
+            self_avoidance_distance: 3,
+            self_avoidance_min_age: 100,
+            self_avoidance_strategy: SelfAvoidanceStrategy::Retreat,
+            self_avoidance_retreat_steps: 20,
+            self_avoidance_max_retreats: 10,
+            enable_backtracking: false,
+            backtracking_interval: 500,
+            max_backtracks: 20,
             max_distance: 3.0,
+            enable_noise_obstacles: false,
+            noise_obstacle_density: 0.3,
+            noise_obstacle_scale: 6.0,
+            noise_obstacle_min_clearance: 3.0,
             waypoint_reached_dist: 250,
             inner_size_probs: RandomDistConfig::new(Some(vec![3, 5]), vec![0.25, 0.75]),
             outer_margin_probs: RandomDistConfig::new(Some(vec![0, 2]), vec![0.5, 0.5]),
             circ_probs: RandomDistConfig::new(Some(vec![0.0, 0.6, 0.8]), vec![0.75, 0.15, 0.05]),
-            skip_min_spacing_sqr: 45,
-            skip_length_bounds: (3, 11),
+            skips: SkipConfig::default(),
+            freeze_skips: SkipConfig::default(),
+            max_skip_density: 0.0,
             min_freeze_size: 0,
             enable_pulse: false,
             pulse_corner_delay: 5,
             pulse_straight_delay: 10,
             pulse_max_kernel_size: 4,
+            enable_smooth_kernel: false,
+            smooth_kernel_max_change: 1,
+            smooth_kernel_interval: 5,
+            start_room: RoomConfig {
+                size: 6,
+                platform_margin: 3,
+            },
+            finish_room: RoomConfig {
+                size: 4,
+                platform_margin: 3,
+            },
+            spawn_count: 0,
+            spawn_layout: SpawnLayout::Line,
+            spawn_protection: false,
+            finish_podium: false,
+            enable_prefabs: false,
+            prefab_prob: 0.01,
+            prefab_min_distance: 100,
+            prefab_probs: RandomDistConfig::new(
+                Some(vec![
+                    "tunnel".to_string(),
+                    "zigzag".to_string(),
+                    "drop".to_string(),
+                ]),
+                vec![0.4, 0.4, 0.2],
+            ),
+            enable_tele_sections: false,
+            tele_section_prob: 0.25,
+            tele_room: RoomConfig {
+                size: 5,
+                platform_margin: 2,
+            },
+            enable_checkpoint_traps: false,
+            checkpoint_trap_min_length: 5,
+            checkpoint_trap_prob: 0.2,
+            enable_speedups: false,
+            speedup_straight_steps: 15,
+            speedup_length: 2,
+            speedup_force: 30,
+            speedup_max_speed: 0,
+            enable_unhookable: false,
+            unhookable_prob: 0.005,
+            unhookable_length_bounds: (4, 12),
+            enable_decoration: false,
+            decoration: DecorationConfig::default(),
             fade_steps: 60,
             fade_max_size: 6,
             fade_min_size: 3,
+            curves: Vec::new(),
         }
     }
 }
@@ -249,6 +1458,7 @@ impl Default for MapConfig {
             ],
             width: 300,
             height: 300,
+            overrides: Vec::new(),
         }
     }
 }
@@ -0,0 +1,146 @@
+use crate::config::{GenerationConfig, MapConfig};
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+
+/// name of the directory inside the users config directory (e.g. ~/.config on linux)
+const CONFIG_DIR_NAME: &str = "gores-mapgen";
+
+/// sub directories of a config directory, same layout as the embedded presets in data/
+const GEN_CONFIG_DIR: &str = "gen_configs";
+const MAP_CONFIG_DIR: &str = "map_configs";
+
+/// minimum time between two checks for changed config files
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// All available generation and map configs. User configs are loaded at runtime from the
+/// config directories and merged over the embedded presets, so presets can be added or
+/// overridden without rebuilding. Configs are identified by their name, configs of later
+/// directories take precedence.
+pub struct ConfigStore {
+    /// directories that are searched for user configs
+    pub config_dirs: Vec<PathBuf>,
+
+    pub gen_configs: HashMap<String, GenerationConfig>,
+    pub map_configs: HashMap<String, MapConfig>,
+
+    /// modification times of all user config files at the last reload
+    file_times: Vec<(PathBuf, Option<SystemTime>)>,
+
+    /// time of the last check for changed config files
+    last_poll: Instant,
+}
+
+impl ConfigStore {
+    /// creates a store using the users config directory and an optional additional directory
+    pub fn new(config_dir: Option<PathBuf>) -> ConfigStore {
+        let mut config_dirs = Vec::new();
+        if let Some(user_config_dir) = dirs::config_dir() {
+            config_dirs.push(user_config_dir.join(CONFIG_DIR_NAME));
+        }
+        config_dirs.extend(config_dir);
+
+        let mut store = ConfigStore {
+            config_dirs,
+            gen_configs: HashMap::new(),
+            map_configs: HashMap::new(),
+            file_times: Vec::new(),
+            last_poll: Instant::now(),
+        };
+        store.reload();
+
+        store
+    }
+
+    /// returns all json files in the given sub directory of all config directories
+    fn get_files(&self, sub_dir: &str) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+
+        for config_dir in self.config_dirs.iter() {
+            let entries = match fs::read_dir(config_dir.join(sub_dir)) {
+                Ok(entries) => entries,
+                Err(_) => continue, // config directories are optional
+            };
+
+            let mut dir_files: Vec<PathBuf> = entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+                .collect();
+            dir_files.sort();
+            files.extend(dir_files);
+        }
+
+        files
+    }
+
+    fn get_file_times(&self) -> Vec<(PathBuf, Option<SystemTime>)> {
+        let mut files = self.get_files(GEN_CONFIG_DIR);
+        files.extend(self.get_files(MAP_CONFIG_DIR));
+
+        files
+            .into_iter()
+            .map(|path| {
+                let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok();
+                (path, modified)
+            })
+            .collect()
+    }
+
+    fn read_file(path: &PathBuf) -> Option<String> {
+        match fs::read_to_string(path) {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!("couldn't read config file {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+
+    /// reloads the embedded presets and merges all user configs over them
+    pub fn reload(&mut self) {
+        // user presets may extend embedded presets, so inheritance is resolved for all at once
+        let mut gen_presets = GenerationConfig::get_embedded_presets();
+        for path in self.get_files(GEN_CONFIG_DIR) {
+            let file_name = path.to_string_lossy().to_string();
+            let data = match ConfigStore::read_file(&path) {
+                Some(data) => data,
+                None => continue,
+            };
+            match GenerationConfig::migrate_json(&data, &file_name) {
+                Ok(value) => gen_presets.push((file_name, value)),
+                Err(e) => warn!("couldn't parse gen config {}: {}", file_name, e),
+            }
+        }
+        self.gen_configs = GenerationConfig::resolve_presets(gen_presets);
+
+        self.map_configs = MapConfig::get_all_configs();
+
+        for path in self.get_files(MAP_CONFIG_DIR) {
+            let config = ConfigStore::read_file(&path)
+                .and_then(|data| MapConfig::parse_preset(&data, &path.to_string_lossy()));
+            if let Some(config) = config {
+                self.map_configs.insert(config.name.clone(), config);
+            }
+        }
+
+        self.file_times = self.get_file_times();
+        info!(
+            "loaded {} gen configs and {} map configs",
+            self.gen_configs.len(),
+            self.map_configs.len()
+        );
+    }
+
+    /// Returns whether user config files were added, removed or modified since the last
+    /// reload. The file system is checked at most once per POLL_INTERVAL.
+    pub fn has_changes(&mut self) -> bool {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return false;
+        }
+        self.last_poll = Instant::now();
+
+        self.get_file_times() != self.file_times
+    }
+}
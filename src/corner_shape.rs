@@ -0,0 +1,346 @@
+use crate::{
+    map::{BlockType, Map},
+    position::{Position, ShiftDirection},
+};
+use log::warn;
+use rust_embed::RustEmbed;
+use serde::{Deserialize, Serialize};
+
+#[derive(RustEmbed)]
+#[folder = "data/corner_shapes/"]
+pub struct CornerShapeStorage;
+
+/// Serialized corner shape. Blocks are defined row by row in a square window with odd size,
+/// using the following chars: `~` freeze, ` ` empty and `.` for any block. The center of the
+/// window is the position of the corner candidate.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct CornerShapeConfig {
+    /// name of the corner shape
+    pub name: String,
+
+    /// block rows of the window, must form a square with odd size
+    pub rows: Vec<String>,
+
+    /// direction from the corner candidate towards the wall
+    pub dir: ShiftDirection,
+
+    /// also detect the shape mirrored along the wall direction
+    pub mirror: bool,
+
+    /// also detect the shape in all four rotations
+    pub rotate: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CornerShape {
+    pub name: String,
+
+    /// required blocks as (x, y) offset relative to the corner candidate
+    pub blocks: Vec<((i32, i32), BlockType)>,
+
+    /// direction from the corner candidate towards the wall
+    pub dir: ShiftDirection,
+}
+
+impl CornerShape {
+    pub fn from_config(config: &CornerShapeConfig) -> Result<CornerShape, &'static str> {
+        let size = config.rows.len();
+        if size % 2 != 1 {
+            return Err("corner shape size must be odd");
+        }
+
+        let radius = (size / 2) as i32;
+        let mut blocks = Vec::new();
+        for (y, row) in config.rows.iter().enumerate() {
+            if row.chars().count() != size {
+                return Err("corner shape must be a square");
+            }
+
+            for (x, char) in row.chars().enumerate() {
+                let offset = (x as i32 - radius, y as i32 - radius);
+                match char {
+                    '~' => blocks.push((offset, BlockType::Freeze)),
+                    ' ' => blocks.push((offset, BlockType::Empty)),
+                    '.' => (),
+                    _ => return Err("corner shape contains unknown block char"),
+                }
+            }
+        }
+
+        Ok(CornerShape {
+            name: config.name.clone(),
+            blocks,
+            dir: config.dir,
+        })
+    }
+
+    /// returns a copy of the shape rotated by 90 degrees clockwise
+    pub fn rotated_cw(&self) -> CornerShape {
+        CornerShape {
+            name: self.name.clone(),
+            blocks: self
+                .blocks
+                .iter()
+                .map(|((x, y), block)| ((-y, *x), block.clone()))
+                .collect(),
+            dir: self.dir.rotated_cw(),
+        }
+    }
+
+    /// returns a copy of the shape mirrored along its wall direction
+    pub fn mirrored(&self) -> CornerShape {
+        let mirror = |(x, y): (i32, i32)| match self.dir {
+            ShiftDirection::Left | ShiftDirection::Right => (x, -y),
+            ShiftDirection::Up | ShiftDirection::Down => (-x, y),
+        };
+
+        CornerShape {
+            name: self.name.clone(),
+            blocks: self
+                .blocks
+                .iter()
+                .map(|(offset, block)| (mirror(*offset), block.clone()))
+                .collect(),
+            dir: self.dir,
+        }
+    }
+
+    /// returns the max distance of a required block to the corner candidate
+    pub fn radius(&self) -> usize {
+        self.blocks
+            .iter()
+            .map(|((x, y), _)| usize::max(x.unsigned_abs() as usize, y.unsigned_abs() as usize))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// checks whether the shape matches the map at the given corner candidate
+    pub fn matches(&self, map: &Map, pos: &Position) -> bool {
+        self.blocks.iter().all(|((x, y), block)| {
+            pos.shifted_by(*x, *y)
+                .ok()
+                .and_then(|pos| map.grid.get(pos.as_index()))
+                == Some(block)
+        })
+    }
+
+    /// generates all variants of a config, symmetric duplicates are removed
+    pub fn get_variants(config: &CornerShapeConfig) -> Result<Vec<CornerShape>, &'static str> {
+        let shape = CornerShape::from_config(config)?;
+
+        let mut bases = vec![shape.clone()];
+        if config.mirror {
+            bases.push(shape.mirrored());
+        }
+
+        let mut variants: Vec<CornerShape> = Vec::new();
+        for mut variant in bases {
+            let rotations = if config.rotate { 4 } else { 1 };
+            for _ in 0..rotations {
+                let rotated = variant.rotated_cw();
+                variant.blocks.sort_by_key(|(offset, _)| *offset);
+                if !variants.contains(&variant) {
+                    variants.push(variant);
+                }
+                variant = rotated;
+            }
+        }
+
+        Ok(variants)
+    }
+
+    /// loads all embedded corner shapes including all their variants
+    pub fn get_all_shapes() -> Vec<CornerShape> {
+        let mut shapes = Vec::new();
+
+        for file_name in CornerShapeStorage::iter() {
+            let file = CornerShapeStorage::get(&file_name).unwrap();
+            let data = std::str::from_utf8(&file.data).unwrap();
+            let variants = serde_json::from_str::<CornerShapeConfig>(data)
+                .map_err(|e| e.to_string())
+                .and_then(|config| CornerShape::get_variants(&config).map_err(|e| e.to_string()));
+
+            match variants {
+                Ok(variants) => shapes.extend(variants),
+                Err(e) => {
+                    warn!("couldn't parse corner shape {}: {}", file_name, e);
+                }
+            }
+        }
+
+        shapes
+    }
+}
+
+/// returns a vec of corner candidates and their respective direction to the wall. Each
+/// candidate is only reported once per direction, even if multiple shapes match.
+pub fn find_corner_candidates(
+    map: &Map,
+    shapes: &[CornerShape],
+) -> Vec<(Position, ShiftDirection)> {
+    let mut candidates: Vec<(Position, ShiftDirection)> = Vec::new();
+
+    let window_size = shapes.iter().map(|shape| shape.radius()).max().unwrap_or(0);
+    if map.width <= 2 * window_size || map.height <= 2 * window_size {
+        return candidates;
+    }
+
+    for window_x in window_size..(map.width - window_size) {
+        for window_y in window_size..(map.height - window_size) {
+            let pos = Position::new(window_x, window_y);
+
+            for dir in [
+                ShiftDirection::Right,
+                ShiftDirection::Left,
+                ShiftDirection::Up,
+                ShiftDirection::Down,
+            ] {
+                if shapes
+                    .iter()
+                    .any(|shape| shape.dir == dir && shape.matches(map, &pos))
+                {
+                    candidates.push((pos.clone(), dir));
+                }
+            }
+        }
+    }
+
+    candidates
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_SIZE: usize = 15;
+
+    /// name, freeze blocks and wall direction of a corner pattern
+    type Pattern = (&'static str, [(usize, usize); 5], ShiftDirection);
+
+    /// sorted blocks and wall direction of a corner shape, for comparing shapes
+    type ShapeKey = (Vec<((i32, i32), BlockType)>, ShiftDirection);
+
+    /// Previously hardcoded corner patterns as freeze blocks in a 5x5 window indexed [x, y],
+    /// the center [2, 2] must be empty.
+    const PATTERNS: [Pattern; 8] = [
+        (
+            "R1",
+            [(2, 3), (3, 0), (3, 1), (3, 2), (3, 3)],
+            ShiftDirection::Right,
+        ),
+        (
+            "R2",
+            [(2, 1), (3, 1), (3, 2), (3, 3), (3, 4)],
+            ShiftDirection::Right,
+        ),
+        (
+            "L1",
+            [(2, 3), (1, 0), (1, 1), (1, 2), (1, 3)],
+            ShiftDirection::Left,
+        ),
+        (
+            "L2",
+            [(2, 1), (1, 1), (1, 2), (1, 3), (1, 4)],
+            ShiftDirection::Left,
+        ),
+        (
+            "U1",
+            [(3, 2), (0, 1), (1, 1), (2, 1), (3, 1)],
+            ShiftDirection::Up,
+        ),
+        (
+            "U2",
+            [(1, 2), (1, 1), (2, 1), (3, 1), (4, 1)],
+            ShiftDirection::Up,
+        ),
+        (
+            "D1",
+            [(3, 2), (0, 3), (1, 3), (2, 3), (3, 3)],
+            ShiftDirection::Down,
+        ),
+        (
+            "D2",
+            [(1, 2), (1, 3), (2, 3), (3, 3), (4, 3)],
+            ShiftDirection::Down,
+        ),
+    ];
+
+    /// hookable map with an empty center and the given freeze blocks of a 5x5 window around it
+    fn pattern_map(freeze: &[(usize, usize)]) -> (Map, Position) {
+        let mut map = Map::new(MAP_SIZE, MAP_SIZE, BlockType::Hookable);
+        let center = Position::new(MAP_SIZE / 2, MAP_SIZE / 2);
+        map.grid[center.as_index()] = BlockType::Empty;
+
+        for (x, y) in freeze {
+            map.grid[[center.x + x - 2, center.y + y - 2]] = BlockType::Freeze;
+        }
+
+        (map, center)
+    }
+
+    #[test]
+    fn variants_match_hardcoded_patterns() {
+        let mut expected: Vec<ShapeKey> = PATTERNS
+            .iter()
+            .map(|(_, freeze, dir)| {
+                let mut blocks: Vec<_> = freeze
+                    .iter()
+                    .map(|(x, y)| ((*x as i32 - 2, *y as i32 - 2), BlockType::Freeze))
+                    .collect();
+                blocks.push(((0, 0), BlockType::Empty));
+                blocks.sort_by_key(|(offset, _)| *offset);
+                (blocks, *dir)
+            })
+            .collect();
+
+        let mut variants: Vec<ShapeKey> = CornerShape::get_all_shapes()
+            .into_iter()
+            .map(|mut shape| {
+                shape.blocks.sort_by_key(|(offset, _)| *offset);
+                (shape.blocks, shape.dir)
+            })
+            .collect();
+
+        let sort_key = |(blocks, dir): &ShapeKey| (format!("{:?}", dir), format!("{:?}", blocks));
+        expected.sort_by_key(sort_key);
+        variants.sort_by_key(sort_key);
+
+        assert_eq!(variants, expected);
+    }
+
+    #[test]
+    fn patterns_detected_in_all_orientations() {
+        let shapes = CornerShape::get_all_shapes();
+
+        for (name, freeze, dir) in PATTERNS {
+            let (map, center) = pattern_map(&freeze);
+            let candidates = find_corner_candidates(&map, &shapes);
+
+            assert_eq!(
+                candidates,
+                vec![(center, dir)],
+                "pattern {} not detected",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn incomplete_patterns_not_detected() {
+        let shapes = CornerShape::get_all_shapes();
+
+        for (name, freeze, _) in PATTERNS {
+            for skipped in 0..freeze.len() {
+                let mut incomplete = freeze.to_vec();
+                incomplete.remove(skipped);
+                let (map, _) = pattern_map(&incomplete);
+
+                assert!(
+                    find_corner_candidates(&map, &shapes).is_empty(),
+                    "incomplete pattern {} detected",
+                    name
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use crate::{config::DecorationConfig, map::Map, random::Random};
+use dt::dt_bool;
+use ndarray::{Array2, Ix2};
+
+/// Background quad in block coordinates, rendered with parallax behind the map.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecorationQuad {
+    /// center of the quad
+    pub pos: (f32, f32),
+
+    /// width and height of the quad
+    pub size: (f32, f32),
+
+    /// rgba color of the quad
+    pub color: (u8, u8, u8, u8),
+}
+
+/// Purely visual map decoration, which has no effect on gameplay. Exported into additional
+/// groups behind the tile layers.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoration {
+    /// blocks covered by the background tile layer
+    pub background: Array2<bool>,
+
+    /// background quads
+    pub quads: Vec<DecorationQuad>,
+
+    /// parallax of the background quads in percent
+    pub quad_parallax: i32,
+
+    /// rgb sky color at the top and bottom of the screen
+    pub sky_colors: ((u8, u8, u8), (u8, u8, u8)),
+}
+
+/// linearly interpolates between two rgb colors
+fn lerp_color(from: (u8, u8, u8), to: (u8, u8, u8), t: f32) -> (u8, u8, u8) {
+    let lerp = |from: u8, to: u8| (from as f32 + (to as f32 - from as f32) * t).round() as u8;
+    (lerp(from.0, to.0), lerp(from.1, to.1), lerp(from.2, to.2))
+}
+
+impl Decoration {
+    /// derives the decoration from the map grid. The background covers all walls and extends
+    /// background_margin blocks into the empty area. Quads are randomly spread over the map
+    /// and colored using a darkened sky gradient at their height.
+    pub fn generate(map: &Map, config: &DecorationConfig, rnd: &mut Random) -> Decoration {
+        let walls = map.grid.map(|block| block.is_solid() || block.is_freeze());
+
+        // euclidean distance to the next wall block
+        let distance = dt_bool::<f32>(&walls.into_dyn())
+            .into_dimensionality::<Ix2>()
+            .unwrap();
+        let background = distance.map(|distance| *distance <= config.background_margin as f32);
+
+        let quad_color = |y: f32| {
+            let t = (y / map.height as f32).clamp(0.0, 1.0);
+            let (r, g, b) = lerp_color(config.sky_top_color, config.sky_bottom_color, t);
+            let (r, g, b) = lerp_color((r, g, b), (0, 0, 0), config.quad_darkening);
+            (r, g, b, 255)
+        };
+
+        // quads with parallax move slower than the map, so they only have to cover a scaled
+        // down area of the map
+        let parallax = config.quad_parallax.clamp(0, 100) as f32 / 100.0;
+        let extend_x = usize::max((map.width as f32 * parallax) as usize, 1);
+        let extend_y = usize::max((map.height as f32 * parallax) as usize, 1);
+
+        let quads = (0..config.quad_count)
+            .map(|_| {
+                let size = rnd
+                    .in_range_inclusive(config.quad_size_bounds.0, config.quad_size_bounds.1)
+                    as f32;
+                let x = rnd.in_range_exclusive(0, extend_x) as f32;
+                let y = rnd.in_range_exclusive(0, extend_y) as f32;
+
+                DecorationQuad {
+                    pos: (x, y),
+                    size: (size, size),
+                    color: quad_color(y / f32::max(parallax, f32::EPSILON)),
+                }
+            })
+            .collect();
+
+        Decoration {
+            background,
+            quads,
+            quad_parallax: config.quad_parallax,
+            sky_colors: (config.sky_top_color, config.sky_bottom_color),
+        }
+    }
+}
@@ -3,9 +3,11 @@ use std::{collections::HashMap, path::PathBuf, str::FromStr};
 const STEPS_PER_FRAME: usize = 50;
 
 use crate::{
-    config::{GenerationConfig, MapConfig},
+    config::{ConfigIssue, GenerationConfig, MapConfig},
+    config_store::ConfigStore,
     generator::Generator,
     gui::{debug_window, sidebar},
+    layout::LayoutType,
     map::Map,
     random::Seed,
 };
@@ -59,8 +61,7 @@ enum PausedState {
 }
 pub struct Editor {
     state: EditorState,
-    pub init_gen_configs: HashMap<String, GenerationConfig>,
-    pub init_map_configs: HashMap<String, MapConfig>,
+    pub configs: ConfigStore,
     pub canvas: Option<egui::Rect>,
     pub egui_wants_mouse: Option<bool>,
     pub average_fps: f32,
@@ -89,16 +90,26 @@ pub struct Editor {
     /// whether to show the GenerationConfig settings
     pub edit_map_config: bool,
 
+    /// procedural layout used when generating a new MapConfig
+    pub layout: LayoutType,
+
     /// asd
     pub visualize_debug_layers: HashMap<&'static str, bool>,
+
+    /// last gen config that passed validation, so edits are only validated once
+    validated_gen_config: Option<GenerationConfig>,
+
+    /// issues of the edited configs and the configs they were found for, so the sidebar only
+    /// validates them again after an edit
+    config_issues: Option<(GenerationConfig, MapConfig, Vec<ConfigIssue>)>,
 }
 
 impl Editor {
-    pub fn new(gen_config: GenerationConfig, map_config: MapConfig) -> Editor {
-        let init_gen_configs: HashMap<String, GenerationConfig> =
-            GenerationConfig::get_all_configs();
-        let init_map_configs: HashMap<String, MapConfig> = MapConfig::get_all_configs();
-
+    pub fn new(
+        gen_config: GenerationConfig,
+        map_config: MapConfig,
+        configs: ConfigStore,
+    ) -> Editor {
         // TODO: its kinda stupid to initialize this as its literally re-initialized anyways
         // when starting the first map generation. But i dont wanna bother adding an Option here as
         // the generator also holds the initial empty map which is used for visualization.
@@ -111,8 +122,7 @@ impl Editor {
 
         Editor {
             state: EditorState::Paused(PausedState::Setup),
-            init_gen_configs,
-            init_map_configs,
+            configs,
             canvas: None,
             egui_wants_mouse: None,
             average_fps: 0.0,
@@ -130,7 +140,10 @@ impl Editor {
             fixed_seed: false,
             edit_gen_config: false,
             edit_map_config: false,
+            layout: LayoutType::Spiral,
             visualize_debug_layers,
+            validated_gen_config: None,
+            config_issues: None,
         }
     }
 
@@ -141,6 +154,37 @@ impl Editor {
 
         // this value is only valid for each frame after calling define_egui()
         self.canvas = None;
+
+        self.reload_configs();
+    }
+
+    /// Reloads all configs if config files changed on disk. The edited configs are replaced
+    /// by their reloaded version, unless they were modified in the editor.
+    fn reload_configs(&mut self) {
+        if !self.configs.has_changes() {
+            return;
+        }
+
+        let gen_config_unedited =
+            self.configs.gen_configs.get(&self.gen_config.name) == Some(&self.gen_config);
+        let map_config_unedited =
+            self.configs.map_configs.get(&self.map_config.name) == Some(&self.map_config);
+
+        self.configs.reload();
+
+        // overrides may refer to reloaded configs, so the issues have to be found again
+        self.config_issues = None;
+
+        if gen_config_unedited {
+            if let Some(gen_config) = self.configs.gen_configs.get(&self.gen_config.name) {
+                self.gen_config = gen_config.clone();
+            }
+        }
+        if map_config_unedited {
+            if let Some(map_config) = self.configs.map_configs.get(&self.map_config.name) {
+                self.map_config = map_config.clone();
+            }
+        }
     }
 
     pub fn get_display_factor(&self, map: &Map) -> f32 {
@@ -190,6 +234,10 @@ impl Editor {
 
     pub fn set_playing(&mut self) {
         if self.is_setup() {
+            // refuse to start a generation with an invalid config
+            if !self.configs_valid() {
+                return;
+            }
             self.on_start();
         }
         self.state = EditorState::Playing(PlayingState::Continuous);
@@ -197,11 +245,62 @@ impl Editor {
 
     pub fn set_single_step(&mut self) {
         if self.is_setup() {
+            // refuse to start a generation with an invalid config
+            if !self.configs_valid() {
+                return;
+            }
             self.on_start();
         }
         self.state = EditorState::Playing(PlayingState::SingleStep);
     }
 
+    /// returns whether a generation can be started with the current configs
+    fn configs_valid(&self) -> bool {
+        self.gen_config.validate().is_ok()
+            && self.map_config.validate(&self.gen_config).is_ok()
+            && self
+                .map_config
+                .resolve_overrides(&self.gen_config, &self.configs.gen_configs)
+                .is_ok()
+    }
+
+    /// Validates the gen config if it has been edited since the last validation. Generator
+    /// steps don't validate the config themselves, so this must be checked before stepping.
+    pub fn validate_gen_config(&mut self) -> Result<(), &'static str> {
+        if self.validated_gen_config.as_ref() != Some(&self.gen_config) {
+            self.gen_config.validate()?;
+            self.validated_gen_config = Some(self.gen_config.clone());
+        }
+
+        Ok(())
+    }
+
+    /// Returns all issues of the edited configs. The configs are only validated again if they
+    /// have been edited since the last call.
+    pub fn config_issues(&mut self) -> &Vec<ConfigIssue> {
+        let up_to_date = self
+            .config_issues
+            .as_ref()
+            .is_some_and(|(gen_config, map_config, _)| {
+                *gen_config == self.gen_config && *map_config == self.map_config
+            });
+
+        if !up_to_date {
+            let mut issues = self.gen_config.validate_all();
+            issues.extend(self.map_config.validate_all(&self.gen_config));
+            if let Err(message) = self
+                .map_config
+                .resolve_overrides(&self.gen_config, &self.configs.gen_configs)
+            {
+                issues.push(ConfigIssue::new("overrides", message));
+            }
+
+            self.config_issues = Some((self.gen_config.clone(), self.map_config.clone(), issues));
+        }
+
+        &self.config_issues.as_ref().unwrap().2
+    }
+
     pub fn set_setup(&mut self) {
         self.state = EditorState::Paused(PausedState::Setup);
     }
@@ -216,6 +315,13 @@ impl Editor {
         }
 
         self.gen = Generator::new(&self.gen_config, &self.map_config, self.user_seed.clone());
+
+        // configs have been validated before starting the generation
+        let waypoint_configs = self
+            .map_config
+            .resolve_overrides(&self.gen_config, &self.configs.gen_configs)
+            .unwrap_or_default();
+        self.gen.set_waypoint_configs(waypoint_configs);
     }
 
     fn mouse_in_viewport(cam: &Camera2D) -> bool {
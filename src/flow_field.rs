@@ -0,0 +1,200 @@
+use crate::{
+    config::GenerationConfig,
+    map::{BlockType, Map},
+    position::{Position, ShiftDirection},
+};
+use ndarray::Array2;
+use noise::{NoiseFn, Perlin};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+
+/// Distances of all blocks to a goal, where walking through noise features or already carved
+/// blocks is more expensive. A walker that follows decreasing distances meanders around noise
+/// features and avoids its previous path, instead of walking in a straight line. Blocks that
+/// are carved after the flow field was computed are only avoided once it is recomputed.
+#[derive(Debug, Clone)]
+pub struct FlowField {
+    /// goal of the flow field, the walker requires a new flow field for each waypoint
+    pub goal: Position,
+
+    /// seed of the noise, recomputed flow fields towards the same goal keep their noise
+    pub noise_seed: u32,
+
+    /// walker steps at the time the flow field was computed
+    pub walker_steps: usize,
+
+    /// cached noise of all blocks in [0, 1], NaN if not sampled yet
+    noise: Array2<f32>,
+
+    /// cost of the cheapest path to the goal for each block, f32::INFINITY if unreachable
+    distances: Array2<f32>,
+}
+
+/// block in the dijkstra queue, ordered such that the smallest distance is popped first
+#[derive(PartialEq)]
+struct QueueEntry {
+    distance: f32,
+    pos: Position,
+}
+
+impl Eq for QueueEntry {}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.distance.total_cmp(&self.distance)
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl FlowField {
+    /// Computes the flow field from start towards goal, see FlowField::update.
+    pub fn new(
+        map: &Map,
+        start: &Position,
+        goal: &Position,
+        margin: usize,
+        config: &GenerationConfig,
+        noise_seed: u32,
+        walker_steps: usize,
+    ) -> FlowField {
+        let mut flow_field = FlowField {
+            goal: goal.clone(),
+            noise_seed,
+            walker_steps,
+            noise: Array2::from_elem((map.width, map.height), f32::NAN),
+            distances: Array2::from_elem((map.width, map.height), f32::INFINITY),
+        };
+        flow_field.update(map, start, margin, config, walker_steps);
+
+        flow_field
+    }
+
+    /// Recomputes the distances from start towards the goal using dijkstra, so that blocks
+    /// carved since the last update are avoided as well. Blocks closer than margin to the map
+    /// border are never entered, so the kernels of the walker stay inside the map. To keep
+    /// this fast for large maps, only blocks inside the bounding box of start and goal are
+    /// considered, which is padded by half their distance to allow for detours.
+    pub fn update(
+        &mut self,
+        map: &Map,
+        start: &Position,
+        margin: usize,
+        config: &GenerationConfig,
+        walker_steps: usize,
+    ) {
+        let goal = &self.goal;
+        let perlin = Perlin::new(self.noise_seed);
+        let scale = config.flow_field_noise_scale as f64;
+
+        let padding = (start.distance_squared(goal) as f32).sqrt() as usize / 2 + margin;
+        let min_x = start.x.min(goal.x).saturating_sub(padding).max(margin);
+        let min_y = start.y.min(goal.y).saturating_sub(padding).max(margin);
+        let max_x = (start.x.max(goal.x) + padding).min(map.width.saturating_sub(margin + 1));
+        let max_y = (start.y.max(goal.y) + padding).min(map.height.saturating_sub(margin + 1));
+
+        // cost of leaving a block, perlin noise is roughly in [-1, 1]. The noise stays the same
+        // for all updates, so it is only sampled once for each block.
+        let noise = &mut self.noise;
+        let costs = Array2::from_shape_fn((map.width, map.height), |(x, y)| {
+            if x < min_x || y < min_y || x > max_x || y > max_y {
+                return f32::INFINITY;
+            }
+
+            if noise[[x, y]].is_nan() {
+                noise[[x, y]] =
+                    (perlin.get([x as f64 / scale, y as f64 / scale]) as f32 + 1.0) / 2.0;
+            }
+
+            let carved_penalty = match map.grid[[x, y]] {
+                BlockType::Empty => config.flow_field_carved_penalty,
+                _ => 0.0,
+            };
+
+            1.0 + config.flow_field_noise_strength * noise[[x, y]].max(0.0) + carved_penalty
+        });
+
+        let distances = &mut self.distances;
+        distances.fill(f32::INFINITY);
+        let mut queue = BinaryHeap::new();
+        distances[goal.as_index()] = 0.0;
+        queue.push(QueueEntry {
+            distance: 0.0,
+            pos: goal.clone(),
+        });
+
+        while let Some(QueueEntry { distance, pos }) = queue.pop() {
+            // block has already been reached via a cheaper path
+            if distance > distances[pos.as_index()] {
+                continue;
+            }
+
+            for shift in [
+                ShiftDirection::Up,
+                ShiftDirection::Right,
+                ShiftDirection::Down,
+                ShiftDirection::Left,
+            ] {
+                let mut neighbor = pos.clone();
+                if neighbor.shift_in_direction(&shift, map).is_err() {
+                    continue;
+                }
+
+                let neighbor_distance = distance + costs[neighbor.as_index()];
+                if neighbor_distance < distances[neighbor.as_index()] {
+                    distances[neighbor.as_index()] = neighbor_distance;
+                    queue.push(QueueEntry {
+                        distance: neighbor_distance,
+                        pos: neighbor,
+                    });
+                }
+            }
+        }
+
+        self.walker_steps = walker_steps;
+    }
+
+    /// returns whether the block after shifting can reach the goal
+    pub fn is_shift_reachable(&self, pos: &Position, shift: &ShiftDirection, map: &Map) -> bool {
+        let mut shifted_pos = pos.clone();
+        shifted_pos.shift_in_direction(shift, map).is_ok()
+            && self.distances[shifted_pos.as_index()].is_finite()
+    }
+
+    /// Returns all shifts sorted by the distance to the goal after shifting, same as
+    /// Position::get_rated_shifts but using the flow field. Shifts with equal distances (e.g.
+    /// unreachable blocks) are sorted by the euclidean distance to the goal.
+    pub fn get_rated_shifts(&self, pos: &Position, map: &Map) -> [ShiftDirection; 4] {
+        let mut shifts = [
+            ShiftDirection::Left,
+            ShiftDirection::Up,
+            ShiftDirection::Right,
+            ShiftDirection::Down,
+        ];
+
+        let rate_shift = |shift: &ShiftDirection| {
+            let mut shifted_pos = pos.clone();
+            match shifted_pos.shift_in_direction(shift, map) {
+                Ok(()) => (
+                    self.distances[shifted_pos.as_index()],
+                    shifted_pos.distance_squared(&self.goal),
+                ),
+                Err(_) => (f32::INFINITY, usize::MAX),
+            }
+        };
+
+        shifts.sort_by(|shift1, shift2| {
+            let (distance1, euclidean1) = rate_shift(shift1);
+            let (distance2, euclidean2) = rate_shift(shift2);
+            distance1
+                .total_cmp(&distance2)
+                .then(euclidean1.cmp(&euclidean2))
+        });
+
+        shifts
+    }
+}
@@ -1,23 +1,44 @@
-use std::{collections::BTreeMap};
+use std::collections::{BTreeMap, HashMap};
+use std::rc::Rc;
 use timing::Timer;
 
 use crate::{
-    config::{GenerationConfig, MapConfig},
+    config::{GenerationConfig, MapConfig, RoomConfig, SpawnLayout, SteeringMode},
+    corner_shape::CornerShape,
     debug::DebugLayer,
+    decoration::Decoration,
+    flow_field::FlowField,
     kernel::Kernel,
-    map::{BlockType, Map, Overwrite},
+    map::{BlockType, Map, Overwrite, Zone},
+    physics_layers::{TeleTile, TeleType},
     position::Position,
     post_processing as post,
+    prefab::Prefab,
     random::{Random, Seed},
     walker::CuteWalker,
 };
 
-use macroquad::{color::colors};
+use log::warn;
+use macroquad::color::colors;
+
+/// Curves are evaluated in discrete progress steps, as evaluating them requires serializing the
+/// entire config. This results in at most CURVE_STEPS config changes along the map.
+const CURVE_STEPS: usize = 100;
 
 pub fn print_time(_timer: &Timer, _message: &str) {
     // println!("{}: {:?}", message, timer.elapsed());
 }
 
+/// generator state before a walker step, which is restored if a later step fails
+struct Checkpoint {
+    walker: CuteWalker,
+    map: Map,
+    rnd: Random,
+    tele_sections: usize,
+    active_waypoint_config: Option<usize>,
+    curve_config: Option<(usize, Rc<GenerationConfig>)>,
+}
+
 pub struct Generator {
     pub walker: CuteWalker,
     pub map: Map,
@@ -26,8 +47,33 @@ pub struct Generator {
     /// PRNG wrapper
     pub rnd: Random,
 
+    /// all available prefabs in all rotations
+    pub prefabs: Vec<Prefab>,
+
+    /// all available corner shapes for skip detection in all variants
+    pub corner_shapes: Vec<CornerShape>,
+
     /// remember where generation began, so a start room can be placed in post processing
     spawn: Position,
+
+    /// number of teleporter sections placed so far, also used as tele and checkpoint number
+    pub tele_sections: usize,
+
+    /// generation configs of waypoint overrides, sorted by the waypoint they start at
+    waypoint_configs: Vec<(usize, Rc<GenerationConfig>)>,
+
+    /// index of the currently active waypoint config, None if the base config is used
+    active_waypoint_config: Option<usize>,
+
+    /// config with evaluated curves and the progress step it has been evaluated at
+    curve_config: Option<(usize, Rc<GenerationConfig>)>,
+
+    /// the two most recent checkpoints, the older one is restored if a step fails. This way the
+    /// walker always backtracks at least backtracking_interval steps.
+    checkpoints: Vec<Checkpoint>,
+
+    /// number of times the generator backtracked to a checkpoint
+    pub backtracks: usize,
 }
 
 pub fn generate_room(
@@ -37,17 +83,16 @@ pub fn generate_room(
     platform_margin: usize,
     zone_type: Option<&BlockType>,
 ) -> Result<(), &'static str> {
-    let room_size: i32 = room_size as i32;
-    let platform_margin: i32 = platform_margin as i32;
-
-    if !map.pos_in_bounds(&pos.shifted_by(room_size + 2, room_size + 1).unwrap())
-        || !map.pos_in_bounds(&pos.shifted_by(room_size + 1, room_size + 1).unwrap())
-    {
+    // room incl. start/finish line must fit into map
+    if !map.square_in_bounds(pos, room_size + 1) {
         return Err("generate room out of bounds");
     }
 
+    let room_size: i32 = room_size as i32;
+    let platform_margin: i32 = platform_margin as i32;
+
     // carve room
-    map.set_area_border(
+    map.set_area(
         &pos.shifted_by(-room_size, -room_size)?,
         &pos.shifted_by(room_size, room_size)?,
         &BlockType::Empty,
@@ -55,11 +100,17 @@ pub fn generate_room(
     );
 
     // only reserve - 1 so that when this is used for platforms
-    map.set_area(
+    map.add_zone(
         &pos.shifted_by(-room_size + 1, -room_size + 1)?,
         &pos.shifted_by(room_size - 1, room_size - 1)?,
-        &BlockType::EmptyReserved,
-        &Overwrite::Force,
+        Zone::ROOM,
+    );
+
+    // but keep the entire room free of obstacles
+    map.add_zone(
+        &pos.shifted_by(-room_size, -room_size)?,
+        &pos.shifted_by(room_size, room_size)?,
+        Zone::NO_OBSTACLE,
     );
 
     // set start/finish line
@@ -68,17 +119,7 @@ pub fn generate_room(
             &pos.shifted_by(-room_size - 1, -room_size - 1)?,
             &pos.shifted_by(room_size + 1, room_size + 1)?,
             zone_type,
-            &Overwrite::ReplaceNonSolidForce,
-        );
-    }
-
-    // set spawns
-    if zone_type == Some(&BlockType::Start) {
-        map.set_area(
-            &pos.shifted_by(-(room_size - platform_margin), room_size - 1)?,
-            &pos.shifted_by(room_size - platform_margin, room_size - 1)?,
-            &BlockType::Spawn,
-            &Overwrite::Force,
+            &Overwrite::ReplaceNonSolid,
         );
     }
 
@@ -105,7 +146,152 @@ pub fn generate_room(
     Ok(())
 }
 
+/// places spawns above the spawn platform of a start room. A spawn_count of 0 fills the
+/// entire platform width.
+pub fn generate_spawns(
+    map: &mut Map,
+    pos: &Position,
+    room: &RoomConfig,
+    spawn_count: usize,
+    layout: SpawnLayout,
+) -> Result<(), &'static str> {
+    let platform_extend = room.size - room.platform_margin;
+    let platform_width = 2 * platform_extend + 1;
+    let spawn_count = match spawn_count {
+        0 => platform_width,
+        count => usize::min(count, platform_width),
+    };
+
+    let platform_left = pos.shifted_by(-(platform_extend as i32), room.size as i32 - 1)?;
+    for spawn_index in 0..spawn_count {
+        let x_offset = match layout {
+            SpawnLayout::Line => (platform_width - spawn_count) / 2 + spawn_index,
+            SpawnLayout::Spread if spawn_count == 1 => platform_width / 2,
+            SpawnLayout::Spread => spawn_index * (platform_width - 1) / (spawn_count - 1),
+        };
+
+        let spawn_pos = platform_left.shifted_by(x_offset as i32, 0)?;
+        map.set_area(&spawn_pos, &spawn_pos, &BlockType::Spawn, &Overwrite::Force);
+    }
+
+    Ok(())
+}
+
+/// places a small decorative podium at the bottom of a room
+pub fn generate_podium(
+    map: &mut Map,
+    pos: &Position,
+    room_size: usize,
+) -> Result<(), &'static str> {
+    let room_size = room_size as i32;
+
+    for step in 0..3 {
+        map.set_area(
+            &pos.shifted_by(-(2 - step), room_size - step)?,
+            &pos.shifted_by(2 - step, room_size - step)?,
+            &BlockType::Platform,
+            &Overwrite::Force,
+        );
+    }
+
+    Ok(())
+}
+
+/// places a pair of tele rooms for a teleporter section. The source room is entirely covered
+/// by tele ins, so it ends the current section. The target room has a platform with checkpoint
+/// tiles, a tele out at its center and a checkpoint out directly above.
+pub fn generate_tele_rooms(
+    map: &mut Map,
+    source_pos: &Position,
+    target_pos: &Position,
+    room: &RoomConfig,
+    number: u8,
+) -> Result<(), &'static str> {
+    let room_size = room.size as i32;
+
+    // tele in room, no platform needed as tees are teleported on entering
+    let top_left = source_pos.shifted_by(-room_size, -room_size)?;
+    let bot_right = source_pos.shifted_by(room_size, room_size)?;
+    map.set_area(&top_left, &bot_right, &BlockType::Empty, &Overwrite::Force);
+    map.add_zone(&top_left, &bot_right, Zone::ROOM);
+    for x in top_left.x..=bot_right.x {
+        for y in top_left.y..=bot_right.y {
+            map.set_tele(
+                &Position::new(x, y),
+                TeleTile {
+                    tele_type: TeleType::In,
+                    number,
+                },
+            );
+        }
+    }
+
+    // tele out room
+    generate_room(map, target_pos, room.size, room.platform_margin, None)?;
+    map.set_tele(
+        target_pos,
+        TeleTile {
+            tele_type: TeleType::Out,
+            number,
+        },
+    );
+    map.set_tele(
+        &target_pos.shifted_by(0, -1)?,
+        TeleTile {
+            tele_type: TeleType::CheckpointOut,
+            number,
+        },
+    );
+
+    // checkpoint tiles directly above the center platform
+    let platform_extend = room_size - room.platform_margin as i32;
+    for x_offset in -platform_extend..=platform_extend {
+        map.set_tele(
+            &target_pos.shifted_by(x_offset, room_size - 4)?,
+            TeleTile {
+                tele_type: TeleType::Checkpoint,
+                number,
+            },
+        );
+    }
+
+    Ok(())
+}
+
 impl Generator {
+    /// teleports the walker to its goal instead of walking there, if a tele section is
+    /// rolled and both tele rooms fit into the map. Returns true if the walker was teleported.
+    fn check_tele_section(&mut self, config: &GenerationConfig) -> Result<bool, &'static str> {
+        // the finish is always reached by walking, tele numbers are limited to u8
+        if !self.walker.has_waypoint_after_goal() || self.tele_sections >= u8::MAX as usize {
+            return Ok(false);
+        }
+
+        if !self.rnd.with_probability(config.tele_section_prob) {
+            return Ok(false);
+        }
+
+        let target_pos = self.walker.goal.clone().unwrap();
+        let room_extend = config.tele_room.size + 1;
+        if !self.map.square_in_bounds(&self.walker.pos, room_extend)
+            || !self.map.square_in_bounds(&target_pos, room_extend)
+        {
+            return Ok(false);
+        }
+
+        self.tele_sections += 1;
+        generate_tele_rooms(
+            &mut self.map,
+            &self.walker.pos.clone(),
+            &target_pos,
+            &config.tele_room,
+            self.tele_sections as u8,
+        )?;
+        self.walker.teleport_to_goal();
+
+        Ok(true)
+    }
+
     /// derive a initial generator state based on a GenerationConfig
     pub fn new(gen_config: &GenerationConfig, map_config: &MapConfig, seed: Seed) -> Generator {
         let map = Map::new(map_config.width, map_config.height, BlockType::Hookable);
@@ -126,26 +312,223 @@ impl Generator {
             ("skips", DebugLayer::new(true, colors::GREEN, &map)),
             ("skips_invalid", DebugLayer::new(true, colors::RED, &map)),
             ("blobs", DebugLayer::new(false, colors::RED, &map)),
+            ("obstacles", DebugLayer::new(false, colors::PURPLE, &map)),
+            (
+                "checkpoint_traps",
+                DebugLayer::new(false, colors::MAGENTA, &map),
+            ),
+            ("speedups", DebugLayer::new(false, colors::SKYBLUE, &map)),
+            ("unhookable", DebugLayer::new(false, colors::GRAY, &map)),
         ]);
 
         Generator {
             walker,
             map,
             rnd,
+            prefabs: Prefab::get_all_prefabs(),
+            corner_shapes: CornerShape::get_all_shapes(),
             debug_layers,
             spawn,
+            tele_sections: 0,
+            waypoint_configs: Vec::new(),
+            active_waypoint_config: None,
+            curve_config: None,
+            checkpoints: Vec::new(),
+            backtracks: 0,
+        }
+    }
+
+    /// sets the resolved generation configs of waypoint overrides, see
+    /// MapConfig::resolve_overrides
+    pub fn set_waypoint_configs(&mut self, waypoint_configs: Vec<(usize, GenerationConfig)>) {
+        self.waypoint_configs = waypoint_configs
+            .into_iter()
+            .map(|(waypoint, config)| (waypoint, Rc::new(config)))
+            .collect();
+        self.active_waypoint_config = None;
+    }
+
+    /// Returns the waypoint config of the path the walker is currently on, or None if the
+    /// base config should be used. The config switches as soon as the walker continues with
+    /// the next waypoint, the PRNG distributions are updated accordingly.
+    fn update_waypoint_config(
+        &mut self,
+        config: &GenerationConfig,
+    ) -> Option<Rc<GenerationConfig>> {
+        // walker is heading towards its goal, so it is on the path starting at the waypoint before
+        let path_start = self.walker.goal_index.saturating_sub(1);
+        let active = self
+            .waypoint_configs
+            .iter()
+            .rposition(|(waypoint, _)| *waypoint <= path_start);
+
+        if active != self.active_waypoint_config {
+            self.active_waypoint_config = active;
+            self.curve_config = None;
+            match active {
+                Some(index) => self.rnd.update_dists(&self.waypoint_configs[index].1),
+                None => self.rnd.update_dists(config),
+            }
+        }
+
+        active.map(|index| self.waypoint_configs[index].1.clone())
+    }
+
+    /// Returns the config with all curves evaluated at the current walker progress, or None
+    /// if the config has no curves. The PRNG distributions are updated with each evaluation,
+    /// as curves may also change the distributions.
+    fn update_curve_config(
+        &mut self,
+        config: &GenerationConfig,
+    ) -> Result<Option<Rc<GenerationConfig>>, &'static str> {
+        if config.curves.is_empty() {
+            // curves may have been removed in the editor during generation
+            if self.curve_config.take().is_some() {
+                self.rnd.update_dists(config);
+            }
+            return Ok(None);
+        }
+
+        let progress_step = (self.walker.progress() * CURVE_STEPS as f32).round() as usize;
+        if let Some((step, curve_config)) = &self.curve_config {
+            if *step == progress_step {
+                return Ok(Some(curve_config.clone()));
+            }
         }
+
+        let curve_config = Rc::new(config.at_progress(progress_step as f32 / CURVE_STEPS as f32)?);
+        self.rnd.update_dists(&curve_config);
+        self.curve_config = Some((progress_step, curve_config.clone()));
+
+        Ok(Some(curve_config))
+    }
+
+    /// computes a new flow field whenever the walker continues with the next waypoint, and
+    /// recomputes it every flow_field_update_interval steps to account for newly carved blocks
+    fn update_flow_field(&mut self, config: &GenerationConfig) {
+        if config.steering != SteeringMode::FlowField {
+            self.walker.flow_field = None;
+            return;
+        }
+
+        let goal = match &self.walker.goal {
+            Some(goal) => goal.clone(),
+            None => return,
+        };
+        // keep some space between the outer kernel and the map border for post processing
+        let margin = config.max_kernel_size() / 2 + 2;
+
+        match &mut self.walker.flow_field {
+            Some(flow_field) if flow_field.goal == goal => {
+                let update_due = config.flow_field_update_interval > 0
+                    && self.walker.steps
+                        >= flow_field.walker_steps + config.flow_field_update_interval;
+                if update_due {
+                    flow_field.update(
+                        &self.map,
+                        &self.walker.pos,
+                        margin,
+                        config,
+                        self.walker.steps,
+                    );
+                }
+            }
+            _ => {
+                self.walker.flow_field = Some(FlowField::new(
+                    &self.map,
+                    &self.walker.pos,
+                    &goal,
+                    margin,
+                    config,
+                    self.rnd.random_u64() as u32,
+                    self.walker.steps,
+                ));
+            }
+        }
+    }
+
+    /// stores a checkpoint every backtracking_interval walker steps, keeping the two most
+    /// recent ones
+    fn update_checkpoints(&mut self, config: &GenerationConfig) {
+        let checkpoint_due = match self.checkpoints.last() {
+            Some(checkpoint) => {
+                self.walker.steps >= checkpoint.walker.steps + config.backtracking_interval
+            }
+            None => true,
+        };
+        if !checkpoint_due {
+            return;
+        }
+
+        self.checkpoints.push(Checkpoint {
+            walker: self.walker.clone(),
+            map: self.map.clone(),
+            rnd: self.rnd.clone(),
+            tele_sections: self.tele_sections,
+            active_waypoint_config: self.active_waypoint_config,
+            curve_config: self.curve_config.clone(),
+        });
+        if self.checkpoints.len() > 2 {
+            self.checkpoints.remove(0);
+        }
+    }
+
+    /// Restores the older checkpoint after a failed step. The PRNG is reseeded using the
+    /// current PRNG state, so that the walker takes a different path than before.
+    fn backtrack(&mut self, error: &'static str) {
+        let seed = self.rnd.random_u64();
+        self.checkpoints.truncate(1);
+        let checkpoint = &self.checkpoints[0];
+
+        self.walker = checkpoint.walker.clone();
+        self.map = checkpoint.map.clone();
+        self.rnd = checkpoint.rnd.clone();
+        self.rnd.reseed(seed);
+        self.tele_sections = checkpoint.tele_sections;
+        self.active_waypoint_config = checkpoint.active_waypoint_config;
+        self.curve_config = checkpoint.curve_config.clone();
+        self.backtracks += 1;
+
+        warn!(
+            "walker step failed ({}), backtracked to step {}",
+            error, self.walker.steps
+        );
     }
 
+    /// Performs a single generation step. If backtracking is enabled, failed steps restore a
+    /// previous checkpoint instead, until max_backtracks is reached. The config must have been
+    /// validated before, which also covers its curves and the waypoint overrides.
     pub fn step(&mut self, config: &GenerationConfig) -> Result<(), &'static str> {
+        if !config.enable_backtracking {
+            return self.step_walker(config);
+        }
+
+        self.update_checkpoints(config);
+        match self.step_walker(config) {
+            Err(error) if self.backtracks < config.max_backtracks => {
+                self.backtrack(error);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    fn step_walker(&mut self, config: &GenerationConfig) -> Result<(), &'static str> {
+        let waypoint_config = self.update_waypoint_config(config);
+        let config = waypoint_config.as_deref().unwrap_or(config);
+        let curve_config = self.update_curve_config(config)?;
+        let config = curve_config.as_deref().unwrap_or(config);
+
         // check if walker has reached goal position
         if self.walker.is_goal_reached(&config.waypoint_reached_dist) == Some(true) {
             self.walker.next_waypoint();
+
+            if config.enable_tele_sections {
+                self.check_tele_section(config)?;
+            }
         }
 
         if !self.walker.finished {
-            config.validate()?; // TODO: how much does this slow down generation?
-
             // randomly mutate kernel
             if self.walker.steps > config.fade_steps {
                 self.walker.mutate_kernel(config, &mut self.rnd);
@@ -159,6 +542,7 @@ impl Generator {
             }
 
             // perform one step
+            self.update_flow_field(config);
             self.walker
                 .probabilistic_step(&mut self.map, config, &mut self.rnd)?;
 
@@ -168,6 +552,12 @@ impl Generator {
                 config.platform_distance_bounds.0,
                 config.platform_distance_bounds.1,
             )?;
+
+            // handle prefabs
+            if config.enable_prefabs {
+                self.walker
+                    .check_prefab(&mut self.map, &self.prefabs, config, &mut self.rnd);
+            }
         }
 
         Ok(())
@@ -180,16 +570,62 @@ impl Generator {
         self.debug_layers.get_mut("edge_bugs").unwrap().grid = edge_bugs;
         print_time(&timer, "fix edge bugs");
 
-        generate_room(&mut self.map, &self.spawn, 6, 3, Some(&BlockType::Start))
-            .expect("start room generation failed");
+        // rooms (incl. start/finish line and optional freeze line) must fit into map
+        let start_room_extend = config.start_room.size + 1 + config.spawn_protection as usize;
+        if !self.map.square_in_bounds(&self.spawn, start_room_extend) {
+            return Err("start room out of bounds");
+        }
+        if !self
+            .map
+            .square_in_bounds(&self.walker.pos, config.finish_room.size + 1)
+        {
+            return Err("finish room out of bounds");
+        }
+
+        generate_room(
+            &mut self.map,
+            &self.spawn,
+            config.start_room.size,
+            config.start_room.platform_margin,
+            Some(&BlockType::Start),
+        )?;
+        generate_spawns(
+            &mut self.map,
+            &self.spawn,
+            &config.start_room,
+            config.spawn_count,
+            config.spawn_layout,
+        )?;
+
+        if config.spawn_protection {
+            let extend = config.start_room.size as i32 + 2;
+            let top_left = self.spawn.shifted_by(-extend, -extend)?;
+            let bot_right = self.spawn.shifted_by(extend, extend)?;
+            self.map.set_area_border(
+                &top_left,
+                &bot_right,
+                &BlockType::Freeze,
+                &Overwrite::ReplaceSolidOnly,
+            );
+            self.map
+                .add_zone_border(&top_left, &bot_right, Zone::SPAWN_PROTECTION);
+        }
+
         generate_room(
             &mut self.map,
             &self.walker.pos.clone(),
-            4,
-            3,
+            config.finish_room.size,
+            config.finish_room.platform_margin,
             Some(&BlockType::Finish),
-        )
-        .expect("start finish room generation");
+        )?;
+
+        if config.finish_podium {
+            generate_podium(
+                &mut self.map,
+                &self.walker.pos.clone(),
+                config.finish_room.size,
+            )?;
+        }
         print_time(&timer, "place rooms");
 
         if config.min_freeze_size > 0 {
@@ -198,25 +634,87 @@ impl Generator {
             print_time(&timer, "detect blobs");
         }
 
+        if config.enable_noise_obstacles {
+            let obstacles = post::generate_noise_obstacles(
+                self,
+                config.noise_obstacle_density,
+                config.noise_obstacle_scale,
+                config.noise_obstacle_min_clearance,
+            );
+            self.debug_layers.get_mut("obstacles").unwrap().grid = obstacles;
+            print_time(&timer, "place noise obstacles");
+        }
+
         post::fill_open_areas(self, &config.max_distance);
         print_time(&timer, "place obstacles");
 
-        post::generate_all_skips(self, config.skip_length_bounds, config.skip_min_spacing_sqr);
+        if config.enable_unhookable {
+            let unhookable = post::generate_unhookable_walls(
+                self,
+                config.unhookable_prob,
+                config.unhookable_length_bounds,
+            );
+            self.debug_layers.get_mut("unhookable").unwrap().grid = unhookable;
+            print_time(&timer, "place unhookable walls");
+        }
+
+        post::generate_all_skips(
+            self,
+            &config.skips,
+            &config.freeze_skips,
+            config.max_skip_density,
+        );
         print_time(&timer, "generate skips");
 
+        if config.enable_checkpoint_traps {
+            let traps = post::generate_checkpoint_traps(
+                self,
+                config.checkpoint_trap_min_length,
+                config.checkpoint_trap_prob,
+            );
+            self.debug_layers.get_mut("checkpoint_traps").unwrap().grid = traps;
+            print_time(&timer, "place checkpoint traps");
+        }
+
+        if config.enable_speedups {
+            let speedups = post::generate_speedups(
+                self,
+                config.speedup_length,
+                config.speedup_force,
+                config.speedup_max_speed,
+            );
+            self.debug_layers.get_mut("speedups").unwrap().grid = speedups;
+            print_time(&timer, "place speedups");
+        }
+
+        if config.enable_decoration {
+            self.map.decoration = Some(Decoration::generate(
+                &self.map,
+                &config.decoration,
+                &mut self.rnd,
+            ));
+            print_time(&timer, "generate decoration");
+        }
+
         Ok(())
     }
 
     /// Generates an entire map with a single function call. This function is used by the CLI.
     /// It is important to keep this function up to date with the editor generation, so that
-    /// fixed seed map generations result in the same map.
+    /// fixed seed map generations result in the same map. Returns the map and the number of
+    /// backtracks that were required to generate it.
     pub fn generate_map(
         max_steps: usize,
         seed: &Seed,
         gen_config: &GenerationConfig,
         map_config: &MapConfig,
-    ) -> Result<Map, &'static str> {
+        gen_configs: &HashMap<String, GenerationConfig>,
+    ) -> Result<(Map, usize), &'static str> {
+        gen_config.validate()?;
+        map_config.validate(gen_config)?;
+        let waypoint_configs = map_config.resolve_overrides(gen_config, gen_configs)?;
         let mut gen = Generator::new(gen_config, map_config, seed.clone());
+        gen.set_waypoint_configs(waypoint_configs);
 
         for _ in 0..max_steps {
             if gen.walker.finished {
@@ -227,6 +725,6 @@ impl Generator {
 
         gen.post_processing(gen_config)?;
 
-        Ok(gen.map)
+        Ok((gen.map, gen.backtracks))
     }
 }
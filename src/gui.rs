@@ -1,10 +1,16 @@
 use std::{collections::HashMap, env, isize};
 
-use egui::RichText;
+use egui::{Color32, Id, RichText};
+use serde_json::Value;
 use tinyfiledialogs;
 
 use crate::{
+    config::{
+        ConfigIssue, CurveConfig, DecorationConfig, RoomConfig, SelfAvoidanceStrategy, SkipConfig,
+        SpawnLayout, SteeringMode,
+    },
     editor::{window_frame, Editor},
+    layout::LayoutType,
     position::{Position, ShiftDirection},
     random::{RandomDistConfig, Seed},
 };
@@ -12,18 +18,36 @@ use egui::Context;
 use egui::{CollapsingHeader, Label, Ui};
 use macroquad::time::get_fps;
 
+/// egui data id of the issues of the edited configs, used to highlight invalid fields
+const CONFIG_ISSUES_ID: &str = "config_issues";
+
+/// label of an edit widget, highlighted if the config field at the given path (e.g. "skips" or
+/// "curves[2].points") has any issues. Widgets that don't edit a config field pass None.
+fn field_label(ui: &Ui, label: &str, field: Option<&str>) -> RichText {
+    let has_issues = field.is_some_and(|field| {
+        ui.data_mut(|data| data.get_temp::<Vec<ConfigIssue>>(Id::new(CONFIG_ISSUES_ID)))
+            .is_some_and(|issues| issues.iter().any(|issue| issue.affects(field)))
+    });
+
+    match has_issues {
+        true => RichText::new(label).color(Color32::RED),
+        false => RichText::new(label),
+    }
+}
+
 pub fn vec_edit_widget<T, F>(
     ui: &mut Ui,
     vec: &mut Vec<T>,
     edit_element: F,
     label: &str,
+    field: Option<&str>,
     collapsed: bool,
     fixed_size: bool,
 ) where
     F: Fn(&mut Ui, &mut T),
     T: Default,
 {
-    CollapsingHeader::new(label)
+    CollapsingHeader::new(field_label(ui, label, field))
         .default_open(!collapsed)
         .show(ui, |ui| {
             ui.vertical(|ui| {
@@ -53,6 +77,7 @@ pub fn random_dist_cfg_edit<T, F>(
     cfg: &mut RandomDistConfig<T>,
     edit_element: Option<F>,
     label: &str,
+    field: Option<&str>,
     collapsed: bool,
     fixed_size: bool,
 ) where
@@ -61,7 +86,7 @@ pub fn random_dist_cfg_edit<T, F>(
 {
     let dist_has_values = cfg.values.is_some();
 
-    CollapsingHeader::new(label)
+    CollapsingHeader::new(field_label(ui, label, field))
         .default_open(!collapsed)
         .show(ui, |ui| {
             ui.vertical(|ui| {
@@ -106,11 +131,12 @@ pub fn hashmap_edit_widget<T, F>(
     hashmap: &mut HashMap<&'static str, T>,
     edit_element: F,
     label: &str,
+    field: Option<&str>,
     collapsed: bool,
 ) where
     F: Fn(&mut Ui, &mut T),
 {
-    CollapsingHeader::new(label)
+    CollapsingHeader::new(field_label(ui, label, field))
         .default_open(!collapsed)
         .show(ui, |ui| {
             ui.vertical(|ui| {
@@ -129,6 +155,7 @@ pub fn field_edit_widget<T, F>(
     value: &mut T,
     edit_element: F,
     label: &str,
+    field: Option<&str>,
     vertical: bool,
 ) where
     F: Fn(&mut Ui, &mut T),
@@ -136,12 +163,12 @@ pub fn field_edit_widget<T, F>(
 {
     if vertical {
         ui.vertical(|ui| {
-            ui.label(label);
+            ui.label(field_label(ui, label, field));
             edit_element(ui, value)
         });
     } else {
         ui.horizontal(|ui| {
-            ui.label(label);
+            ui.label(field_label(ui, label, field));
             edit_element(ui, value)
         });
     }
@@ -163,6 +190,10 @@ pub fn edit_usize(ui: &mut Ui, value: &mut usize) {
     ui.add(egui::DragValue::new(value));
 }
 
+pub fn edit_u8(ui: &mut Ui, value: &mut u8) {
+    ui.add(egui::DragValue::new(value));
+}
+
 pub fn edit_pos_i32(ui: &mut Ui, value: &mut i32) {
     ui.add(egui::DragValue::new(value).clamp_range(0..=isize::max_value()));
 }
@@ -172,6 +203,14 @@ pub fn edit_f32_wtf(ui: &mut Ui, value: &mut f32) {
     ui.add(egui::Slider::new(value, 0.0..=15.0));
 }
 
+pub fn edit_f32(ui: &mut Ui, value: &mut f32) {
+    ui.add(
+        egui::DragValue::new(value)
+            .speed(0.1)
+            .clamp_range(0.0..=f32::MAX),
+    );
+}
+
 pub fn edit_f32_prob(ui: &mut Ui, value: &mut f32) {
     ui.spacing_mut().slider_width = 75.0;
     ui.add(
@@ -236,6 +275,156 @@ pub fn edit_bool(ui: &mut Ui, value: &mut bool) {
     ui.add(egui::Checkbox::new(value, ""));
 }
 
+pub fn edit_room_config(ui: &mut Ui, room: &mut RoomConfig) {
+    ui.horizontal(|ui| {
+        ui.label("size:");
+        ui.add(egui::widgets::DragValue::new(&mut room.size));
+        ui.label("margin:");
+        ui.add(egui::widgets::DragValue::new(&mut room.platform_margin).clamp_range(0..=room.size));
+    });
+}
+
+pub fn edit_skip_config(ui: &mut Ui, skip: &mut SkipConfig) {
+    ui.vertical(|ui| {
+        edit_range_usize(ui, &mut skip.length_bounds);
+        ui.horizontal(|ui| {
+            ui.label("spacing sqr:");
+            ui.add(egui::widgets::DragValue::new(&mut skip.min_spacing_sqr));
+            ui.label("max count:");
+            ui.add(egui::widgets::DragValue::new(&mut skip.max_count));
+        });
+        ui.horizontal(|ui| {
+            ui.label("prob:");
+            edit_f32_prob(ui, &mut skip.prob);
+        });
+        ui.horizontal(|ui| {
+            ui.label("diagonal:");
+            edit_bool(ui, &mut skip.enable_diagonal);
+            ui.label("l-shaped:");
+            edit_bool(ui, &mut skip.enable_l_shaped);
+        });
+    });
+}
+
+pub fn edit_rgb(ui: &mut Ui, value: &mut (u8, u8, u8)) {
+    let mut rgb = [value.0, value.1, value.2];
+    ui.color_edit_button_srgb(&mut rgb);
+    *value = (rgb[0], rgb[1], rgb[2]);
+}
+
+pub fn edit_decoration_config(ui: &mut Ui, decoration: &mut DecorationConfig) {
+    ui.vertical(|ui| {
+        ui.horizontal(|ui| {
+            ui.label("sky top:");
+            edit_rgb(ui, &mut decoration.sky_top_color);
+            ui.label("sky bottom:");
+            edit_rgb(ui, &mut decoration.sky_bottom_color);
+        });
+        ui.horizontal(|ui| {
+            ui.label("background margin:");
+            ui.add(egui::widgets::DragValue::new(
+                &mut decoration.background_margin,
+            ));
+        });
+        ui.horizontal(|ui| {
+            ui.label("quads:");
+            ui.add(egui::widgets::DragValue::new(&mut decoration.quad_count));
+            ui.label("parallax:");
+            ui.add(
+                egui::widgets::DragValue::new(&mut decoration.quad_parallax).clamp_range(0..=100),
+            );
+        });
+        edit_range_usize(ui, &mut decoration.quad_size_bounds);
+        ui.horizontal(|ui| {
+            ui.label("darkening:");
+            edit_f32_prob(ui, &mut decoration.quad_darkening);
+        });
+    });
+}
+
+pub fn edit_curve_point(ui: &mut Ui, point: &mut (f32, f32)) {
+    ui.horizontal(|ui| {
+        ui.label("progress:");
+        edit_f32_prob(ui, &mut point.0);
+        ui.label("value:");
+        ui.add(egui::DragValue::new(&mut point.1).speed(0.01));
+    });
+}
+
+/// edits all curves, unlike vec_edit_widget all curves can be removed
+pub fn edit_curves(ui: &mut Ui, curves: &mut Vec<CurveConfig>) {
+    CollapsingHeader::new(field_label(ui, "curves", Some("curves")))
+        .default_open(false)
+        .show(ui, |ui| {
+            for (index, curve) in curves.iter_mut().enumerate() {
+                ui.push_id(index, |ui| {
+                    field_edit_widget(
+                        ui,
+                        &mut curve.field,
+                        edit_string,
+                        "field",
+                        Some(&format!("curves[{}].field", index)),
+                        false,
+                    );
+                    vec_edit_widget(
+                        ui,
+                        &mut curve.points,
+                        edit_curve_point,
+                        "points",
+                        Some(&format!("curves[{}].points", index)),
+                        false,
+                        false,
+                    );
+                });
+            }
+
+            ui.horizontal(|ui| {
+                if ui.button("+").clicked() {
+                    curves.push(CurveConfig {
+                        field: String::new(),
+                        points: vec![(0.0, 0.0)],
+                    });
+                };
+
+                if ui.button("-").clicked() {
+                    curves.pop();
+                };
+            });
+        });
+}
+
+pub fn edit_steering_mode(ui: &mut Ui, steering: &mut SteeringMode) {
+    egui::ComboBox::from_id_source("steering")
+        .selected_text(format!("{:?}", steering))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(steering, SteeringMode::Greedy, "Greedy");
+            ui.selectable_value(steering, SteeringMode::FlowField, "FlowField");
+        });
+}
+
+pub fn edit_self_avoidance_strategy(ui: &mut Ui, strategy: &mut SelfAvoidanceStrategy) {
+    egui::ComboBox::from_id_source("self_avoidance_strategy")
+        .selected_text(format!("{:?}", strategy))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(strategy, SelfAvoidanceStrategy::Retreat, "Retreat");
+            ui.selectable_value(
+                strategy,
+                SelfAvoidanceStrategy::ForceThrough,
+                "ForceThrough",
+            );
+            ui.selectable_value(strategy, SelfAvoidanceStrategy::Abort, "Abort");
+        });
+}
+
+pub fn edit_spawn_layout(ui: &mut Ui, layout: &mut SpawnLayout) {
+    egui::ComboBox::from_id_source("spawn_layout")
+        .selected_text(format!("{:?}", layout))
+        .show_ui(ui, |ui| {
+            ui.selectable_value(layout, SpawnLayout::Line, "Line");
+            ui.selectable_value(layout, SpawnLayout::Spread, "Spread");
+        });
+}
+
 pub fn sidebar(ctx: &Context, editor: &mut Editor) {
     egui::SidePanel::right("right_panel").show(ctx, |ui| {
         // =======================================[ STATE CONTROL ]===================================
@@ -271,7 +460,14 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
         // =======================================[ SPEED CONTROL ]===================================
         ui.horizontal(|ui| {
             ui.add_enabled_ui(!editor.instant, |ui| {
-                field_edit_widget(ui, &mut editor.steps_per_frame, edit_usize, "speed", true);
+                field_edit_widget(
+                    ui,
+                    &mut editor.steps_per_frame,
+                    edit_usize,
+                    "speed",
+                    None,
+                    true,
+                );
             });
             ui.vertical(|ui| {
                 ui.checkbox(&mut editor.instant, "instant");
@@ -313,6 +509,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
             &mut editor.visualize_debug_layers,
             edit_bool,
             "debug layers",
+            None,
             true,
         );
 
@@ -339,7 +536,17 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                 if let Some(path_out) =
                     tinyfiledialogs::save_file_dialog("save gen config", &initial_path)
                 {
-                    editor.gen_config.save(&path_out);
+                    // configs extending a preset only store their difference to the preset
+                    let parent = editor
+                        .gen_config
+                        .extends
+                        .as_ref()
+                        .and_then(|parent_name| editor.configs.gen_configs.get(parent_name));
+
+                    match parent {
+                        Some(parent) => editor.gen_config.save_diff(&path_out, parent),
+                        None => editor.gen_config.save(&path_out),
+                    }
                 }
             };
 
@@ -363,7 +570,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
         egui::ComboBox::from_label("")
             .selected_text(format!("{:}", editor.gen_config.name))
             .show_ui(ui, |ui| {
-                for (name, cfg) in editor.init_gen_configs.iter() {
+                for (name, cfg) in editor.configs.gen_configs.iter() {
                     ui.selectable_value(&mut editor.gen_config, cfg.clone(), name);
                 }
             });
@@ -371,28 +578,75 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
         egui::ComboBox::from_label(" ")
             .selected_text(format!("{:}", editor.map_config.name))
             .show_ui(ui, |ui| {
-                for (name, cfg) in editor.init_map_configs.iter() {
+                for (name, cfg) in editor.configs.map_configs.iter() {
                     ui.selectable_value(&mut editor.map_config, cfg.clone(), name);
                 }
             });
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_source("layout")
+                .selected_text(editor.layout.name())
+                .show_ui(ui, |ui| {
+                    for layout in LayoutType::ALL {
+                        ui.selectable_value(&mut editor.layout, layout, layout.name());
+                    }
+                });
+            if ui.button("generate layout").clicked() {
+                let seed = Seed::from_random(&mut editor.gen.rnd);
+                editor.map_config = editor.layout.generate(
+                    editor.map_config.width,
+                    editor.map_config.height,
+                    &seed,
+                );
+            }
+        });
 
         ui.horizontal(|ui| {
             ui.checkbox(&mut editor.edit_gen_config, "edit gen");
             ui.checkbox(&mut editor.edit_map_config, "edit map");
         });
 
+        let issues = editor.config_issues().clone();
+        for issue in issues.iter() {
+            ui.colored_label(Color32::RED, issue.to_string());
+        }
+        ui.data_mut(|data| data.insert_temp(Id::new(CONFIG_ISSUES_ID), issues));
+
         egui::ScrollArea::vertical().show(ui, |ui| {
             // =======================================[ GENERATION CONFIG EDIT ]===================================
             if editor.edit_gen_config {
                 ui.separator();
 
-                field_edit_widget(ui, &mut editor.gen_config.name, edit_string, "name", false);
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.name,
+                    edit_string,
+                    "name",
+                    Some("name"),
+                    false,
+                );
+
+                let extends_text = editor.gen_config.extends.as_deref().unwrap_or("none");
+                egui::ComboBox::from_label("extends")
+                    .selected_text(extends_text.to_string())
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut editor.gen_config.extends, None, "none");
+                        for name in editor.configs.gen_configs.keys() {
+                            if *name != editor.gen_config.name {
+                                ui.selectable_value(
+                                    &mut editor.gen_config.extends,
+                                    Some(name.clone()),
+                                    name,
+                                );
+                            }
+                        }
+                    });
 
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.inner_rad_mut_prob,
                     edit_f32_prob,
                     "inner rad mut prob",
+                    Some("inner_rad_mut_prob"),
                     true,
                 );
                 field_edit_widget(
@@ -400,6 +654,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.inner_size_mut_prob,
                     edit_f32_prob,
                     "inner size mut prob",
+                    Some("inner_size_mut_prob"),
                     true,
                 );
 
@@ -408,6 +663,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.outer_rad_mut_prob,
                     edit_f32_prob,
                     "outer rad mut prob",
+                    Some("outer_rad_mut_prob"),
                     true,
                 );
                 field_edit_widget(
@@ -415,6 +671,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.outer_size_mut_prob,
                     edit_f32_prob,
                     "outer size mut prob",
+                    Some("outer_size_mut_prob"),
                     true,
                 );
 
@@ -424,6 +681,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                         &mut editor.gen_config.inner_size_probs,
                         Some(edit_usize),
                         "inner size probs",
+                        Some("inner_size_probs"),
                         true,
                         false,
                     );
@@ -433,6 +691,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                         &mut editor.gen_config.outer_margin_probs,
                         Some(edit_usize),
                         "outer margin probs",
+                        Some("outer_margin_probs"),
                         true,
                         false,
                     );
@@ -441,7 +700,8 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                         ui,
                         &mut editor.gen_config.circ_probs,
                         Some(edit_f32_prob),
-                        "circularity probs",
+                        "circ probs",
+                        Some("circ_probs"),
                         true,
                         false,
                     );
@@ -451,7 +711,8 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     ui,
                     &mut editor.gen_config.platform_distance_bounds,
                     edit_range_usize,
-                    "platform distances",
+                    "platform distance bounds",
+                    Some("platform_distance_bounds"),
                     true,
                 );
 
@@ -460,14 +721,186 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.momentum_prob,
                     edit_f32_prob,
                     "momentum prob",
+                    Some("momentum_prob"),
                     true,
                 );
 
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.steering,
+                    edit_steering_mode,
+                    "steering",
+                    Some("steering"),
+                    false,
+                );
+
+                if editor.gen_config.steering == SteeringMode::FlowField {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.flow_field_noise_scale,
+                        edit_f32,
+                        "flow field noise scale",
+                        Some("flow_field_noise_scale"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.flow_field_noise_strength,
+                        edit_f32_wtf,
+                        "flow field noise strength",
+                        Some("flow_field_noise_strength"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.flow_field_carved_penalty,
+                        edit_f32,
+                        "flow field carved penalty",
+                        Some("flow_field_carved_penalty"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.flow_field_update_interval,
+                        edit_usize,
+                        "flow field update interval",
+                        Some("flow_field_update_interval"),
+                        true,
+                    );
+                }
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_self_avoidance,
+                    edit_bool,
+                    "enable self avoidance",
+                    Some("enable_self_avoidance"),
+                    false,
+                );
+
+                if editor.gen_config.enable_self_avoidance {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.self_avoidance_distance,
+                        edit_usize,
+                        "self avoidance distance",
+                        Some("self_avoidance_distance"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.self_avoidance_min_age,
+                        edit_usize,
+                        "self avoidance min age",
+                        Some("self_avoidance_min_age"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.self_avoidance_strategy,
+                        edit_self_avoidance_strategy,
+                        "self avoidance strategy",
+                        Some("self_avoidance_strategy"),
+                        false,
+                    );
+
+                    if editor.gen_config.self_avoidance_strategy == SelfAvoidanceStrategy::Retreat {
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.self_avoidance_retreat_steps,
+                            edit_usize,
+                            "self avoidance retreat steps",
+                            Some("self_avoidance_retreat_steps"),
+                            true,
+                        );
+
+                        field_edit_widget(
+                            ui,
+                            &mut editor.gen_config.self_avoidance_max_retreats,
+                            edit_usize,
+                            "self avoidance max retreats",
+                            Some("self_avoidance_max_retreats"),
+                            true,
+                        );
+                    }
+                }
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_backtracking,
+                    edit_bool,
+                    "enable backtracking",
+                    Some("enable_backtracking"),
+                    false,
+                );
+
+                if editor.gen_config.enable_backtracking {
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.backtracking_interval,
+                        edit_usize,
+                        "backtracking interval",
+                        Some("backtracking_interval"),
+                        true,
+                    );
+
+                    field_edit_widget(
+                        ui,
+                        &mut editor.gen_config.max_backtracks,
+                        edit_usize,
+                        "max backtracks",
+                        Some("max_backtracks"),
+                        true,
+                    );
+                }
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.max_distance,
                     edit_f32_wtf,
                     "max distance",
+                    Some("max_distance"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_noise_obstacles,
+                    edit_bool,
+                    "enable noise obstacles",
+                    Some("enable_noise_obstacles"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.noise_obstacle_density,
+                    edit_f32_prob,
+                    "noise obstacle density",
+                    Some("noise_obstacle_density"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.noise_obstacle_scale,
+                    edit_f32_wtf,
+                    "noise obstacle scale",
+                    Some("noise_obstacle_scale"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.noise_obstacle_min_clearance,
+                    edit_f32_wtf,
+                    "noise obstacle min clearance",
+                    Some("noise_obstacle_min_clearance"),
                     true,
                 );
 
@@ -476,6 +909,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.waypoint_reached_dist,
                     edit_usize,
                     "waypoint reached dist",
+                    Some("waypoint_reached_dist"),
                     true,
                 );
 
@@ -484,7 +918,8 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                         ui,
                         &mut editor.gen_config.shift_weights,
                         None::<fn(&mut Ui, &mut ShiftDirection)>, // TODO: this is stupid wtwf
-                        "step weights",
+                        "shift weights",
+                        Some("shift_weights"),
                         false,
                         true,
                     );
@@ -492,25 +927,37 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
 
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.skip_length_bounds,
-                    edit_range_usize,
-                    "skip length bounds",
+                    &mut editor.gen_config.skips,
+                    edit_skip_config,
+                    "skips",
+                    Some("skips"),
                     true,
                 );
 
                 field_edit_widget(
                     ui,
-                    &mut editor.gen_config.skip_min_spacing_sqr,
-                    edit_usize,
-                    "skip min spacing sqr",
+                    &mut editor.gen_config.freeze_skips,
+                    edit_skip_config,
+                    "freeze skips",
+                    Some("freeze_skips"),
                     true,
                 );
 
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.max_skip_density,
+                    edit_f32_wtf,
+                    "max skip density",
+                    Some("max_skip_density"),
+                    false,
+                );
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.min_freeze_size,
                     edit_usize,
                     "min freeze size",
+                    Some("min_freeze_size"),
                     false,
                 );
 
@@ -519,6 +966,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.enable_pulse,
                     edit_bool,
                     "enable pulse",
+                    Some("enable_pulse"),
                     false,
                 );
 
@@ -527,6 +975,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.pulse_straight_delay,
                     edit_usize,
                     "pulse straight delay",
+                    Some("pulse_straight_delay"),
                     true,
                 );
 
@@ -535,6 +984,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.pulse_corner_delay,
                     edit_usize,
                     "pulse corner delay",
+                    Some("pulse_corner_delay"),
                     false,
                 );
 
@@ -542,15 +992,281 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     ui,
                     &mut editor.gen_config.pulse_max_kernel_size,
                     edit_usize,
-                    "pulse max kernel",
+                    "pulse max kernel size",
+                    Some("pulse_max_kernel_size"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_smooth_kernel,
+                    edit_bool,
+                    "enable smooth kernel",
+                    Some("enable_smooth_kernel"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.smooth_kernel_max_change,
+                    edit_usize,
+                    "smooth kernel max change",
+                    Some("smooth_kernel_max_change"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.smooth_kernel_interval,
+                    edit_usize,
+                    "smooth kernel interval",
+                    Some("smooth_kernel_interval"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.start_room,
+                    edit_room_config,
+                    "start room",
+                    Some("start_room"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.finish_room,
+                    edit_room_config,
+                    "finish room",
+                    Some("finish_room"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.spawn_count,
+                    edit_usize,
+                    "spawn count",
+                    Some("spawn_count"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.spawn_layout,
+                    edit_spawn_layout,
+                    "spawn layout",
+                    Some("spawn_layout"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.spawn_protection,
+                    edit_bool,
+                    "spawn protection",
+                    Some("spawn_protection"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.finish_podium,
+                    edit_bool,
+                    "finish podium",
+                    Some("finish_podium"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_prefabs,
+                    edit_bool,
+                    "enable prefabs",
+                    Some("enable_prefabs"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.prefab_prob,
+                    edit_f32_prob,
+                    "prefab prob",
+                    Some("prefab_prob"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.prefab_min_distance,
+                    edit_usize,
+                    "prefab min distance",
+                    Some("prefab_min_distance"),
                     false,
                 );
 
+                ui.add_enabled_ui(editor.is_setup(), |ui| {
+                    random_dist_cfg_edit(
+                        ui,
+                        &mut editor.gen_config.prefab_probs,
+                        Some(edit_string),
+                        "prefab probs",
+                        Some("prefab_probs"),
+                        true,
+                        false,
+                    );
+                });
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_tele_sections,
+                    edit_bool,
+                    "enable tele sections",
+                    Some("enable_tele_sections"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.tele_section_prob,
+                    edit_f32_prob,
+                    "tele section prob",
+                    Some("tele_section_prob"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.tele_room,
+                    edit_room_config,
+                    "tele room",
+                    Some("tele_room"),
+                    true,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_checkpoint_traps,
+                    edit_bool,
+                    "enable checkpoint traps",
+                    Some("enable_checkpoint_traps"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.checkpoint_trap_min_length,
+                    edit_usize,
+                    "checkpoint trap min length",
+                    Some("checkpoint_trap_min_length"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.checkpoint_trap_prob,
+                    edit_f32_prob,
+                    "checkpoint trap prob",
+                    Some("checkpoint_trap_prob"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_speedups,
+                    edit_bool,
+                    "enable speedups",
+                    Some("enable_speedups"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.speedup_straight_steps,
+                    edit_usize,
+                    "speedup straight steps",
+                    Some("speedup_straight_steps"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.speedup_length,
+                    edit_usize,
+                    "speedup length",
+                    Some("speedup_length"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.speedup_force,
+                    edit_u8,
+                    "speedup force",
+                    Some("speedup_force"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.speedup_max_speed,
+                    edit_u8,
+                    "speedup max speed",
+                    Some("speedup_max_speed"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_unhookable,
+                    edit_bool,
+                    "enable unhookable",
+                    Some("enable_unhookable"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.unhookable_prob,
+                    edit_f32_prob,
+                    "unhookable prob",
+                    Some("unhookable_prob"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.unhookable_length_bounds,
+                    edit_range_usize,
+                    "unhookable length bounds",
+                    Some("unhookable_length_bounds"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.enable_decoration,
+                    edit_bool,
+                    "enable decoration",
+                    Some("enable_decoration"),
+                    false,
+                );
+
+                field_edit_widget(
+                    ui,
+                    &mut editor.gen_config.decoration,
+                    edit_decoration_config,
+                    "decoration",
+                    Some("decoration"),
+                    true,
+                );
+
                 field_edit_widget(
                     ui,
                     &mut editor.gen_config.fade_steps,
                     edit_usize,
                     "fade steps",
+                    Some("fade_steps"),
                     false,
                 );
 
@@ -559,6 +1275,7 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.fade_max_size,
                     edit_usize,
                     "fade max size",
+                    Some("fade_max_size"),
                     false,
                 );
 
@@ -567,25 +1284,37 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                     &mut editor.gen_config.fade_min_size,
                     edit_usize,
                     "fade min size",
+                    Some("fade_min_size"),
                     false,
                 );
+
+                edit_curves(ui, &mut editor.gen_config.curves);
             }
 
             // =======================================[ MAP CONFIG EDIT ]===================================
             if editor.edit_map_config {
-                field_edit_widget(ui, &mut editor.map_config.name, edit_string, "name", false);
+                field_edit_widget(
+                    ui,
+                    &mut editor.map_config.name,
+                    edit_string,
+                    "name",
+                    Some("name"),
+                    false,
+                );
                 field_edit_widget(
                     ui,
                     &mut editor.map_config.width,
                     edit_usize,
-                    "map width",
+                    "width",
+                    Some("width"),
                     true,
                 );
                 field_edit_widget(
                     ui,
                     &mut editor.map_config.height,
                     edit_usize,
-                    "map height",
+                    "height",
+                    Some("height"),
                     true,
                 );
                 ui.add_enabled_ui(editor.is_setup(), |ui| {
@@ -594,10 +1323,27 @@ pub fn sidebar(ctx: &Context, editor: &mut Editor) {
                         &mut editor.map_config.waypoints,
                         edit_position,
                         "waypoints",
+                        Some("waypoints"),
                         true,
                         false,
                     );
                 });
+
+                // overrides are only edited in the config files, as they contain arbitrary fields
+                if !editor.map_config.overrides.is_empty() {
+                    CollapsingHeader::new(field_label(ui, "overrides", Some("overrides")))
+                        .default_open(false)
+                        .show(ui, |ui| {
+                            for waypoint_override in editor.map_config.overrides.iter() {
+                                ui.label(format!(
+                                    "waypoint {}: {} {}",
+                                    waypoint_override.waypoint,
+                                    waypoint_override.gen_config.as_deref().unwrap_or("-"),
+                                    Value::Object(waypoint_override.fields.clone())
+                                ));
+                            }
+                        });
+                }
             }
         });
     });
@@ -614,6 +1360,10 @@ pub fn debug_window(ctx: &Context, editor: &mut Editor) {
                 editor.average_fps.round() as usize
             )));
             ui.add(Label::new(format!("seed: {:?}", editor.user_seed)));
+            ui.add(Label::new(format!(
+                "backtracks: {:}",
+                editor.gen.backtracks
+            )));
             ui.add(Label::new(format!("config: {:?}", &editor.gen_config)));
             ui.add(Label::new(format!("walker: {:?}", &editor.gen.walker)));
         });
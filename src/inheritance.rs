@@ -0,0 +1,101 @@
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+
+/// Merges a child preset over its parent. Objects are merged recursively, all other values
+/// (incl. lists) of the child replace the values of the parent.
+pub fn merge_json(parent: &Value, child: &Value) -> Value {
+    match (parent, child) {
+        (Value::Object(parent), Value::Object(child)) => {
+            let mut merged = parent.clone();
+            for (key, child_value) in child.iter() {
+                let merged_value = match parent.get(key) {
+                    Some(parent_value) => merge_json(parent_value, child_value),
+                    None => child_value.clone(),
+                };
+                merged.insert(key.clone(), merged_value);
+            }
+            Value::Object(merged)
+        }
+        _ => child.clone(),
+    }
+}
+
+/// Returns only the parts of a preset that differ from its parent, so that merging the diff
+/// over the parent results in the preset again. Returns None if there is no difference.
+pub fn diff_json(parent: &Value, config: &Value) -> Option<Value> {
+    match (parent, config) {
+        (Value::Object(parent), Value::Object(config)) => {
+            let diff: Map<String, Value> = config
+                .iter()
+                .filter_map(|(key, value)| match parent.get(key) {
+                    Some(parent_value) => {
+                        diff_json(parent_value, value).map(|diff| (key.clone(), diff))
+                    }
+                    None => Some((key.clone(), value.clone())),
+                })
+                .collect();
+
+            match diff.is_empty() {
+                true => None,
+                false => Some(Value::Object(diff)),
+            }
+        }
+        _ if parent == config => None,
+        _ => Some(config.clone()),
+    }
+}
+
+/// Resolves the inheritance of presets given by name. Presets may extend another preset using
+/// the "extends" field. Returns the merged preset for each name, or an error if a parent
+/// doesn't exist or the inheritance is cyclic.
+pub fn resolve_inheritance(
+    presets: &HashMap<String, Value>,
+) -> HashMap<String, Result<Value, String>> {
+    let mut resolved = HashMap::new();
+
+    for name in presets.keys() {
+        let _ = resolve_preset(name, presets, &mut Vec::new(), &mut resolved);
+    }
+
+    resolved
+}
+
+/// resolves a single preset, chain contains all presets that are currently being resolved
+fn resolve_preset(
+    name: &str,
+    presets: &HashMap<String, Value>,
+    chain: &mut Vec<String>,
+    resolved: &mut HashMap<String, Result<Value, String>>,
+) -> Result<Value, String> {
+    if let Some(result) = resolved.get(name) {
+        return result.clone();
+    }
+
+    if chain.iter().any(|chain_name| chain_name == name) {
+        return Err(format!(
+            "cyclic inheritance {} -> {}",
+            chain.join(" -> "),
+            name
+        ));
+    }
+
+    let preset = match presets.get(name) {
+        Some(preset) => preset,
+        None => return Err(format!("parent preset {} doesn't exist", name)),
+    };
+
+    let result = match preset.get("extends") {
+        Some(Value::String(parent_name)) => {
+            chain.push(name.to_string());
+            let parent = resolve_preset(parent_name, presets, chain, resolved);
+            chain.pop();
+
+            parent.map(|parent| merge_json(&parent, preset))
+        }
+        Some(Value::Null) | None => Ok(preset.clone()),
+        Some(_) => Err("extends must be the name of a preset".to_string()),
+    };
+
+    resolved.insert(name.to_string(), result.clone());
+    result
+}
@@ -1,6 +1,44 @@
+use crate::position::ShiftDirection;
 use derivative::Derivative;
 use ndarray::Array2;
 
+/// Sub-tile anchor of a walker. Odd sized kernels have a center cell, so they are always placed
+/// centered on the walker tile. The center of an even sized kernel lies on a tile corner, the
+/// anchor defines which corner of the walker tile is used.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct KernelAnchor {
+    /// use right instead of left corner
+    pub right: bool,
+
+    /// use bottom instead of top corner
+    pub down: bool,
+}
+
+impl KernelAnchor {
+    /// moves the anchor to the tile corner(s) facing the given shift direction, so that
+    /// corridors walked in opposite directions are mirror images of each other
+    pub fn lean_towards(&mut self, shift: &ShiftDirection) {
+        match shift {
+            ShiftDirection::Up => self.down = false,
+            ShiftDirection::Right => self.right = true,
+            ShiftDirection::Down => self.down = true,
+            ShiftDirection::Left => self.right = false,
+        }
+    }
+
+    /// (x, y) offset of the kernel center wrt. the center of the walker tile
+    pub fn center_offset(&self, kernel_size: usize) -> (f32, f32) {
+        if kernel_size % 2 == 1 {
+            return (0.0, 0.0);
+        }
+
+        (
+            if self.right { 0.5 } else { -0.5 },
+            if self.down { 0.5 } else { -0.5 },
+        )
+    }
+}
+
 #[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct Kernel {
@@ -29,7 +67,34 @@ impl Kernel {
         }
     }
 
+    /// returns how many cells the kernel extends to the top/left and bot/right of the walker tile
+    /// as ((left, top), (right, bot)), so that size = left + 1 + right = top + 1 + bot.
+    pub fn get_extends(&self, anchor: &KernelAnchor) -> ((usize, usize), (usize, usize)) {
+        let (left, right) = Kernel::axis_extends(self.size, anchor.right);
+        let (top, bot) = Kernel::axis_extends(self.size, anchor.down);
+
+        ((left, top), (right, bot))
+    }
+
+    /// extends of a kernel on a single axis, even sized kernels extend one cell further into the
+    /// positive (right/down) direction if lean_positive is set.
+    fn axis_extends(size: usize, lean_positive: bool) -> (usize, usize) {
+        let half = size / 2;
+
+        if size % 2 == 1 {
+            (half, half)
+        } else if lean_positive {
+            (half.saturating_sub(1), half)
+        } else {
+            (half, half.saturating_sub(1))
+        }
+    }
+
+    /// Center of a kernel vector in cell coordinates, so the vector is point symmetric around
+    /// it. For odd sizes this is the center cell, for even sizes it lies on the corner between
+    /// the four middle cells (e.g. 1.5 for size 4). Kernels must not be empty.
     pub fn kernel_center(kernel_size: usize) -> f32 {
+        assert!(kernel_size > 0, "kernel size must be larger than zero");
         (kernel_size - 1) as f32 / 2.0
     }
 
@@ -61,3 +126,69 @@ impl Kernel {
         kernel
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ndarray::s;
+
+    const MAX_KERNEL_SIZE: usize = 9;
+    const CIRCULARITIES: [f32; 4] = [0.0, 0.3, 0.6, 1.0];
+
+    #[test]
+    fn kernel_center_between_middle_cells() {
+        assert_eq!(Kernel::kernel_center(1), 0.0);
+        assert_eq!(Kernel::kernel_center(2), 0.5);
+        assert_eq!(Kernel::kernel_center(3), 1.0);
+        assert_eq!(Kernel::kernel_center(4), 1.5);
+
+        for size in 1..=MAX_KERNEL_SIZE {
+            let center = Kernel::kernel_center(size);
+            match size % 2 {
+                1 => assert_eq!(center, (size / 2) as f32),
+                _ => assert_eq!(center, (size / 2) as f32 - 0.5),
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn kernel_center_empty_kernel() {
+        Kernel::kernel_center(0);
+    }
+
+    #[test]
+    fn kernel_vector_symmetric_around_center() {
+        for size in 1..=MAX_KERNEL_SIZE {
+            for circularity in CIRCULARITIES {
+                let kernel = Kernel::new(size, circularity);
+                let mirrored = kernel.vector.slice(s![..;-1, ..]);
+                let point_mirrored = kernel.vector.slice(s![..;-1, ..;-1]);
+
+                assert_eq!(kernel.vector, mirrored, "not symmetric: {:?}", kernel);
+                assert_eq!(kernel.vector, point_mirrored, "not symmetric: {:?}", kernel);
+                assert_eq!(
+                    kernel.vector,
+                    kernel.vector.t(),
+                    "not symmetric: {:?}",
+                    kernel
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn extends_sum_up_to_size() {
+        for size in 1..=MAX_KERNEL_SIZE {
+            let kernel = Kernel::new(size, 0.0);
+
+            for (right, down) in [(false, false), (true, false), (false, true), (true, true)] {
+                let anchor = KernelAnchor { right, down };
+                let ((left, top), (right, bot)) = kernel.get_extends(&anchor);
+
+                assert_eq!(left + 1 + right, size);
+                assert_eq!(top + 1 + bot, size);
+            }
+        }
+    }
+}
@@ -0,0 +1,360 @@
+use crate::{config::MapConfig, position::Position, random::Seed};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::TAU;
+
+/// distance of all waypoints to the map border, same as for the hand placed layouts
+const BORDER_MARGIN: usize = 50;
+
+/// amount of candidates that are tried around each point during poisson disc sampling
+const POISSON_CANDIDATES: usize = 30;
+
+/// Procedural waypoint layouts, which can be used to create MapConfigs instead of placing
+/// waypoints by hand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum LayoutType {
+    /// rectangular spiral from the map border towards the center
+    Spiral,
+
+    /// from left to right, alternating between top and bottom of the map
+    Zigzag,
+
+    /// rows filling the map, alternating between left to right and right to left
+    Snake,
+
+    /// tour through randomly spread points with a minimum distance (poisson disc sampling)
+    PoissonTour,
+
+    /// climb from the bottom to the top of a narrow column
+    Tower,
+}
+
+/// area of valid waypoint positions as (left, top, right, bot)
+type Area = (usize, usize, usize, usize);
+
+impl LayoutType {
+    pub const ALL: [LayoutType; 5] = [
+        LayoutType::Spiral,
+        LayoutType::Zigzag,
+        LayoutType::Snake,
+        LayoutType::PoissonTour,
+        LayoutType::Tower,
+    ];
+
+    /// name of the layout, prefixed to avoid conflicts with map config presets
+    pub fn name(&self) -> &'static str {
+        match self {
+            LayoutType::Spiral => "random_spiral",
+            LayoutType::Zigzag => "random_zigzag",
+            LayoutType::Snake => "random_snake",
+            LayoutType::PoissonTour => "random_tour",
+            LayoutType::Tower => "random_tower",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<LayoutType> {
+        LayoutType::ALL
+            .into_iter()
+            .find(|layout| layout.name() == name)
+    }
+
+    /// default map size (width, height) of the layout
+    pub fn default_size(&self) -> (usize, usize) {
+        match self {
+            LayoutType::Spiral => (500, 500),
+            LayoutType::Zigzag => (800, 300),
+            LayoutType::Snake => (400, 400),
+            LayoutType::PoissonTour => (500, 500),
+            LayoutType::Tower => (300, 1000),
+        }
+    }
+
+    /// creates a MapConfig with a procedural waypoint layout for the given map size and seed
+    pub fn generate(&self, width: usize, height: usize, seed: &Seed) -> MapConfig {
+        let mut rng = SmallRng::seed_from_u64(seed.seed_u64);
+
+        // shrink margin for small maps, MapConfig validation reports if they are too small
+        let margin = BORDER_MARGIN.min(width / 4).min(height / 4);
+        let area = (
+            margin,
+            margin,
+            (width - margin).saturating_sub(1),
+            (height - margin).saturating_sub(1),
+        );
+
+        let mut waypoints = match self {
+            LayoutType::Spiral => spiral(&mut rng, area),
+            LayoutType::Zigzag => zigzag(&mut rng, area),
+            LayoutType::Snake => snake(&mut rng, area),
+            LayoutType::PoissonTour => poisson_tour(&mut rng, area),
+            LayoutType::Tower => tower(&mut rng, area),
+        };
+
+        // randomly mirror layouts for more variety, towers must still climb upwards
+        let flip_x = rng.gen_bool(0.5);
+        let flip_y = rng.gen_bool(0.5) && *self != LayoutType::Tower;
+        for waypoint in waypoints.iter_mut() {
+            if flip_x {
+                waypoint.x = area.0 + area.2 - waypoint.x;
+            }
+            if flip_y {
+                waypoint.y = area.1 + area.3 - waypoint.y;
+            }
+        }
+        waypoints.dedup();
+
+        MapConfig {
+            name: format!("{}_{}", self.name(), seed.seed_u64),
+            waypoints,
+            width,
+            height,
+            overrides: Vec::new(),
+        }
+    }
+}
+
+/// samples a distance in [min, max], both bounds are reduced to limit for small maps
+fn sample_distance(rng: &mut SmallRng, min: usize, max: usize, limit: usize) -> usize {
+    let limit = limit.max(1);
+    rng.gen_range(min.min(limit)..=max.min(limit))
+}
+
+/// starts at the bottom left and walks along the border of a shrinking rectangle
+fn spiral(rng: &mut SmallRng, area: Area) -> Vec<Position> {
+    let (mut left, mut top, mut right, mut bot) = area;
+    let gap = sample_distance(rng, 60, 100, (right - left).min(bot - top) / 2);
+
+    let mut waypoints = vec![Position::new(left, bot)];
+    while left + gap <= right && top + gap <= bot {
+        waypoints.push(Position::new(left, top));
+        waypoints.push(Position::new(right, top));
+        waypoints.push(Position::new(right, bot));
+        waypoints.push(Position::new(left + gap, bot));
+
+        left += gap;
+        top += gap;
+        right -= gap;
+        bot -= gap;
+    }
+
+    waypoints
+}
+
+/// moves from left to right while alternating between the top and bottom third of the area
+fn zigzag(rng: &mut SmallRng, area: Area) -> Vec<Position> {
+    let (left, top, right, bot) = area;
+    let band = (bot - top) / 3;
+
+    let mut waypoints = Vec::new();
+    let mut x = left;
+    let mut at_top = rng.gen_bool(0.5);
+    while x <= right {
+        let y = match at_top {
+            true => rng.gen_range(top..=top + band),
+            false => rng.gen_range(bot - band..=bot),
+        };
+        waypoints.push(Position::new(x, y));
+
+        at_top = !at_top;
+        x += sample_distance(rng, 50, 100, (right - left) / 2);
+    }
+
+    waypoints
+}
+
+/// fills the area with horizontal rows from the bottom to the top
+fn snake(rng: &mut SmallRng, area: Area) -> Vec<Position> {
+    let (left, top, right, bot) = area;
+    let gap = sample_distance(rng, 60, 100, (bot - top) / 2);
+
+    let mut waypoints = Vec::new();
+    let mut y = bot;
+    let mut left_to_right = true;
+    loop {
+        let (start_x, end_x) = match left_to_right {
+            true => (left, right),
+            false => (right, left),
+        };
+        waypoints.push(Position::new(start_x, y));
+        waypoints.push(Position::new(end_x, y));
+
+        if y < top + gap {
+            break;
+        }
+        y -= gap;
+        left_to_right = !left_to_right;
+    }
+
+    waypoints
+}
+
+/// Spreads points over the area using poisson disc sampling (Bridson's algorithm), so all
+/// points keep a minimum distance. The points are then visited in nearest neighbour order,
+/// starting at the lowest point.
+fn poisson_tour(rng: &mut SmallRng, area: Area) -> Vec<Position> {
+    let (left, top, right, bot) = area;
+    let radius = sample_distance(rng, 80, 120, (right - left).min(bot - top) / 2) as f32;
+
+    // background grid, each cell contains at most one point
+    let cell_size = radius / 2.0_f32.sqrt();
+    let grid_width = ((right - left) as f32 / cell_size) as usize + 1;
+    let grid_height = ((bot - top) as f32 / cell_size) as usize + 1;
+    let mut grid: Vec<Option<usize>> = vec![None; grid_width * grid_height];
+    let to_cell = |(x, y): (f32, f32)| {
+        (
+            ((x - left as f32) / cell_size) as usize,
+            ((y - top as f32) / cell_size) as usize,
+        )
+    };
+
+    let first = (
+        rng.gen_range(left..=right) as f32,
+        rng.gen_range(top..=bot) as f32,
+    );
+    let (cell_x, cell_y) = to_cell(first);
+    grid[cell_y * grid_width + cell_x] = Some(0);
+    let mut points = vec![first];
+    let mut active = vec![0];
+
+    while !active.is_empty() {
+        let active_index = rng.gen_range(0..active.len());
+        let (x, y) = points[active[active_index]];
+        let mut found = false;
+
+        for _ in 0..POISSON_CANDIDATES {
+            let angle = rng.gen_range(0.0..TAU);
+            let distance = rng.gen_range(radius..2.0 * radius);
+            let candidate = (x + angle.cos() * distance, y + angle.sin() * distance);
+
+            if candidate.0 < left as f32
+                || candidate.1 < top as f32
+                || candidate.0 > right as f32
+                || candidate.1 > bot as f32
+            {
+                continue;
+            }
+
+            // only the surrounding cells can contain points closer than radius
+            let (cell_x, cell_y) = to_cell(candidate);
+            let too_close = (cell_x.saturating_sub(2)..=(cell_x + 2).min(grid_width - 1))
+                .flat_map(|x| {
+                    (cell_y.saturating_sub(2)..=(cell_y + 2).min(grid_height - 1))
+                        .map(move |y| (x, y))
+                })
+                .filter_map(|(x, y)| grid[y * grid_width + x])
+                .any(|index| {
+                    let (other_x, other_y) = points[index];
+                    (other_x - candidate.0).hypot(other_y - candidate.1) < radius
+                });
+
+            if !too_close {
+                grid[cell_y * grid_width + cell_x] = Some(points.len());
+                active.push(points.len());
+                points.push(candidate);
+                found = true;
+                break;
+            }
+        }
+
+        if !found {
+            active.swap_remove(active_index);
+        }
+    }
+
+    let mut remaining: Vec<Position> = points
+        .into_iter()
+        .map(|(x, y)| Position::new(x.round() as usize, y.round() as usize))
+        .collect();
+
+    let start_index = (0..remaining.len())
+        .max_by_key(|index| remaining[*index].y)
+        .unwrap();
+    let mut waypoints = vec![remaining.swap_remove(start_index)];
+    while !remaining.is_empty() {
+        let last = waypoints.last().unwrap();
+        let next_index = (0..remaining.len())
+            .min_by_key(|index| remaining[*index].distance_squared(last))
+            .unwrap();
+        waypoints.push(remaining.swap_remove(next_index));
+    }
+
+    waypoints
+}
+
+/// climbs from the bottom to the top, alternating between both sides of a centered column
+fn tower(rng: &mut SmallRng, area: Area) -> Vec<Position> {
+    let (left, top, right, bot) = area;
+    let column_width = (right - left).min(rng.gen_range(100..=200));
+    let column_left = left + (right - left - column_width) / 2;
+    let column_right = column_left + column_width;
+
+    let mut waypoints = Vec::new();
+    let mut y = bot;
+    let mut at_left = rng.gen_bool(0.5);
+    while y > top {
+        let x = match at_left {
+            true => column_left,
+            false => column_right,
+        };
+        waypoints.push(Position::new(x, y));
+
+        at_left = !at_left;
+        y = y.saturating_sub(rng.gen_range(60..=120)).max(top);
+    }
+    waypoints.push(Position::new((column_left + column_right) / 2, top));
+
+    waypoints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::GenerationConfig;
+
+    const SEEDS: u64 = 20;
+    const SMALL_SIZE: (usize, usize) = (150, 150);
+
+    fn check_layout(layout: LayoutType, width: usize, height: usize) {
+        let gen_config = GenerationConfig::default();
+
+        for seed in 0..SEEDS {
+            let map_config = layout.generate(width, height, &Seed::from_u64(seed));
+            let waypoints = &map_config.waypoints;
+
+            assert!(
+                waypoints.iter().all(|pos| pos.x < width && pos.y < height),
+                "{} waypoint outside of the map: {:?}",
+                map_config.name,
+                waypoints
+            );
+            assert!(
+                waypoints.windows(2).all(|pair| pair[0] != pair[1]),
+                "{} has duplicate neighbours: {:?}",
+                map_config.name,
+                waypoints
+            );
+            assert_eq!(
+                map_config.validate_all(&gen_config),
+                Vec::new(),
+                "{} is invalid: {:?}",
+                map_config.name,
+                waypoints
+            );
+        }
+    }
+
+    #[test]
+    fn layouts_valid_at_default_size() {
+        for layout in LayoutType::ALL {
+            let (width, height) = layout.default_size();
+            check_layout(layout, width, height);
+        }
+    }
+
+    #[test]
+    fn layouts_valid_at_small_size() {
+        for layout in LayoutType::ALL {
+            check_layout(layout, SMALL_SIZE.0, SMALL_SIZE.1);
+        }
+    }
+}
@@ -1,13 +1,22 @@
 pub mod config;
+pub mod config_store;
+pub mod corner_shape;
 pub mod debug;
+pub mod decoration;
 pub mod editor;
+pub mod flow_field;
 pub mod fps_control;
 pub mod generator;
 pub mod gui;
+pub mod inheritance;
 pub mod kernel;
+pub mod layout;
 pub mod map;
+pub mod migration;
+pub mod physics_layers;
 pub mod position;
 pub mod post_processing;
+pub mod prefab;
 pub mod random;
 pub mod rendering;
 pub mod twmap_export;
@@ -3,15 +3,18 @@
 use clap::{crate_version, Parser};
 use gores_mapgen_rust::{
     config::{GenerationConfig, MapConfig},
+    config_store::ConfigStore,
     editor::*,
     fps_control::*,
     map::*,
     rendering::*,
 };
+use log::warn;
 use macroquad::{color::*, miniquad, window::*};
 use miniquad::conf::{Conf, Platform};
 use simple_logger::SimpleLogger;
 use std::panic::{self, AssertUnwindSafe};
+use std::path::PathBuf;
 
 const DISABLE_VSYNC: bool = true;
 
@@ -26,6 +29,10 @@ struct Args {
     /// enable instant, auto generate and fixed seed
     #[arg(short, long)]
     testing: bool,
+
+    /// additional directory with user configs in gen_configs/ and map_configs/
+    #[arg(long)]
+    config_dir: Option<PathBuf>,
 }
 
 fn window_conf() -> Conf {
@@ -50,6 +57,7 @@ async fn main() {
     let mut editor = Editor::new(
         GenerationConfig::get_initial_config(true),
         MapConfig::get_initial_config(),
+        ConfigStore::new(args.config_dir),
     );
     let mut fps_ctrl = FPSControl::new().with_max_fps(60);
 
@@ -61,8 +69,15 @@ async fn main() {
     }
 
     if let Some(config_name) = args.config {
-        if editor.init_gen_configs.contains_key(&config_name) {
-            editor.gen_config = editor.init_gen_configs.get(&config_name).unwrap().clone();
+        if editor.configs.gen_configs.contains_key(&config_name) {
+            editor.gen_config = editor
+                .configs
+                .gen_configs
+                .get(&config_name)
+                .unwrap()
+                .clone();
+        } else {
+            warn!("gen config {} doesn't exist or is invalid", config_name);
         }
     }
 
@@ -75,6 +90,14 @@ async fn main() {
             editor.set_playing();
         }
 
+        // config might have been edited during the generation
+        if editor.is_playing() {
+            editor.validate_gen_config().unwrap_or_else(|err| {
+                println!("Invalid Config: {:}", err);
+                editor.set_setup();
+            });
+        }
+
         // perform walker step
         let steps = match editor.instant {
             true => usize::max_value(),
@@ -121,9 +144,11 @@ async fn main() {
         // draw_grid_blocks(&editor.gen.map.grid);
         draw_chunked_grid(
             &editor.gen.map.grid,
+            &editor.gen.map.zones,
             &editor.gen.map.chunk_edited,
             editor.gen.map.chunk_size,
         );
+        draw_physics_layers(&editor.gen.map);
         draw_walker_kernel(&editor.gen.walker, KernelType::Outer);
         draw_walker_kernel(&editor.gen.walker, KernelType::Inner);
         draw_walker(&editor.gen.walker);
@@ -1,6 +1,14 @@
-use crate::{kernel::Kernel, position::Position, twmap_export::TwExport, walker::CuteWalker};
+use crate::{
+    decoration::Decoration,
+    kernel::Kernel,
+    physics_layers::{PhysicsLayer, SpeedupTile, SwitchTile, TeleTile, TuneTile},
+    position::Position,
+    twmap_export::TwExport,
+    walker::CuteWalker,
+};
 use ndarray::{s, Array2};
 
+use std::ops::{BitOr, BitOrAssign};
 use std::path::PathBuf;
 
 const CHUNK_SIZE: usize = 5;
@@ -8,6 +16,7 @@ const CHUNK_SIZE: usize = 5;
 #[derive(PartialEq)]
 pub enum BlockTypeTW {
     Hookable,
+    Unhookable,
     Freeze,
     Empty,
 }
@@ -15,9 +24,8 @@ pub enum BlockTypeTW {
 #[derive(Debug, Clone, PartialEq)]
 pub enum BlockType {
     Empty,
-    /// Empty Block that should not be overwritten
-    EmptyReserved,
     Hookable,
+    Unhookable,
     Freeze,
     Spawn,
     Start,
@@ -29,8 +37,9 @@ impl BlockType {
     /// maps BlockType to tw game layer id for map export
     pub fn to_tw_game_id(&self) -> u8 {
         match self {
-            BlockType::Empty | BlockType::EmptyReserved => 0,
+            BlockType::Empty => 0,
             BlockType::Hookable | BlockType::Platform => 1,
+            BlockType::Unhookable => 3,
             BlockType::Freeze => 9,
             BlockType::Spawn => 192,
             BlockType::Start => 33,
@@ -41,7 +50,8 @@ impl BlockType {
     pub fn to_tw_block_type(&self) -> BlockTypeTW {
         match self {
             BlockType::Platform | BlockType::Hookable => BlockTypeTW::Hookable,
-            BlockType::Empty | BlockType::EmptyReserved => BlockTypeTW::Empty,
+            BlockType::Unhookable => BlockTypeTW::Unhookable,
+            BlockType::Empty => BlockTypeTW::Empty,
             BlockType::Freeze => BlockTypeTW::Freeze,
 
             // every other block is just mapped to empty
@@ -50,7 +60,15 @@ impl BlockType {
     }
 
     pub fn is_solid(&self) -> bool {
-        matches!(self, BlockType::Hookable | BlockType::Platform)
+        matches!(
+            self,
+            BlockType::Hookable | BlockType::Unhookable | BlockType::Platform
+        )
+    }
+
+    /// hookable or unhookable wall block, platforms are not considered as walls
+    pub fn is_wall(&self) -> bool {
+        matches!(self, BlockType::Hookable | BlockType::Unhookable)
     }
     pub fn is_freeze(&self) -> bool {
         matches!(self, BlockType::Freeze)
@@ -61,10 +79,10 @@ pub enum Overwrite {
     /// Replace EVERYTHING
     Force,
 
-    /// Replace Hookable+Freeze
+    /// Replace Hookable+Unhookable+Freeze
     ReplaceSolidFreeze,
 
-    /// Replace Hookable
+    /// Replace Hookable+Unhookable
     ReplaceSolidOnly,
 
     /// Replace Empty
@@ -72,37 +90,92 @@ pub enum Overwrite {
 
     /// Replace Freeze+Empty
     ReplaceNonSolid,
-
-    /// Replace Freeze+Empty+EmptyReserved
-    ReplaceNonSolidForce,
 }
 
 impl Overwrite {
     fn will_override(&self, btype: &BlockType) -> bool {
         match self {
             Overwrite::Force => true,
-            Overwrite::ReplaceSolidFreeze => {
-                matches!(&btype, BlockType::Hookable | BlockType::Freeze)
-            }
-            Overwrite::ReplaceSolidOnly => matches!(&btype, BlockType::Hookable),
+            Overwrite::ReplaceSolidFreeze => btype.is_wall() || btype.is_freeze(),
+            Overwrite::ReplaceSolidOnly => btype.is_wall(),
             Overwrite::ReplaceEmptyOnly => matches!(&btype, BlockType::Empty),
             Overwrite::ReplaceNonSolid => matches!(&btype, BlockType::Freeze | BlockType::Empty),
-            Overwrite::ReplaceNonSolidForce => matches!(
-                &btype,
-                BlockType::Freeze | BlockType::Empty | BlockType::EmptyReserved
-            ),
         }
     }
 }
 
+/// Zone flags of a block. Zones don't change the block type, but protect blocks from being
+/// modified by generation passes. Multiple zones can be combined using `|`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Zone(u8);
+
+impl Zone {
+    pub const NONE: Zone = Zone(0);
+
+    /// generic protected area, e.g. the walker path during fading
+    pub const RESERVED: Zone = Zone(1 << 0);
+
+    /// inside of start, finish and platform rooms
+    pub const ROOM: Zone = Zone(1 << 1);
+
+    /// blocks of generated skips
+    pub const SKIP: Zone = Zone(1 << 2);
+
+    /// blocks of stamped prefabs
+    pub const PREFAB: Zone = Zone(1 << 3);
+
+    /// blocks that must stay as they are, so no noise obstacles are placed and open areas are
+    /// not filled here. Other passes are unaffected.
+    pub const NO_OBSTACLE: Zone = Zone(1 << 4);
+
+    /// freeze line around the start room
+    pub const SPAWN_PROTECTION: Zone = Zone(1 << 5);
+
+    /// zones that are respected by all post processing passes
+    pub const PROTECTED: Zone = Zone(
+        Zone::RESERVED.0 | Zone::ROOM.0 | Zone::SKIP.0 | Zone::PREFAB.0 | Zone::SPAWN_PROTECTION.0,
+    );
+
+    /// checks whether any flag of other is set
+    pub fn intersects(&self, other: Zone) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Zone {
+    type Output = Zone;
+
+    fn bitor(self, rhs: Zone) -> Zone {
+        Zone(self.0 | rhs.0)
+    }
+}
+
+impl BitOrAssign for Zone {
+    fn bitor_assign(&mut self, rhs: Zone) {
+        self.0 |= rhs.0;
+    }
+}
+
 pub enum KernelType {
     Outer,
     Inner,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Map {
     pub grid: Array2<BlockType>,
+    pub zones: Array2<Zone>,
+
+    /// optional additional physics layers, only allocated once a tile is placed
+    pub front: Option<PhysicsLayer<BlockType>>,
+    pub tele: Option<PhysicsLayer<TeleTile>>,
+    pub speedup: Option<PhysicsLayer<SpeedupTile>>,
+    pub switch: Option<PhysicsLayer<SwitchTile>>,
+    pub tune: Option<PhysicsLayer<TuneTile>>,
+
+    /// optional visual decoration, only used for the export
+    pub decoration: Option<Decoration>,
+
     pub height: usize,
     pub width: usize,
     pub chunk_edited: Array2<bool>, // TODO: make this optional in case editor is not used!
@@ -124,10 +197,31 @@ fn get_maps_path() -> PathBuf {
     }
 }
 
+/// sets a tile of an optional physics layer, the layer is allocated on first use
+fn set_layer_tile<T: Clone>(
+    layer: &mut Option<PhysicsLayer<T>>,
+    width: usize,
+    height: usize,
+    pos: &Position,
+    tile: T,
+) {
+    let layer = layer.get_or_insert_with(|| Array2::from_elem((width, height), None));
+    if let Some(current) = layer.get_mut(pos.as_index()) {
+        *current = Some(tile);
+    }
+}
+
 impl Map {
     pub fn new(width: usize, height: usize, default: BlockType) -> Map {
         Map {
             grid: Array2::from_elem((width, height), default),
+            zones: Array2::from_elem((width, height), Zone::NONE),
+            front: None,
+            tele: None,
+            speedup: None,
+            switch: None,
+            tune: None,
+            decoration: None,
             width,
             height,
             chunk_edited: Array2::from_elem(
@@ -144,26 +238,16 @@ impl Map {
         kernel: &Kernel,
         block_type: BlockType,
     ) -> Result<(), &'static str> {
-        let offset: usize = kernel.size / 2; // offset of kernel wrt. position (top/left)
-        let extend: usize = kernel.size - offset; // how much kernel extends position (bot/right)
-
-        let exceeds_left_bound = walker.pos.x < offset;
-        let exceeds_upper_bound = walker.pos.y < offset;
-        let exceeds_right_bound = (walker.pos.x + extend) > self.width;
-        let exceeds_lower_bound = (walker.pos.y + extend) > self.height;
-
-        if exceeds_left_bound || exceeds_upper_bound || exceeds_right_bound || exceeds_lower_bound {
-            return Err("Kernel out of bounds");
-        }
-
-        let root_pos = Position::new(walker.pos.x - offset, walker.pos.y - offset);
+        let root_pos = self.get_kernel_root(walker, kernel)?;
         for ((kernel_x, kernel_y), kernel_active) in kernel.vector.indexed_iter() {
             let absolute_pos = Position::new(root_pos.x + kernel_x, root_pos.y + kernel_y);
             if *kernel_active {
                 let current_type = &self.grid[absolute_pos.as_index()];
 
                 let new_type = match current_type {
-                    BlockType::Hookable | BlockType::Freeze => Some(block_type.clone()),
+                    BlockType::Hookable | BlockType::Unhookable | BlockType::Freeze => {
+                        Some(block_type.clone())
+                    }
                     _ => None,
                 };
 
@@ -179,6 +263,47 @@ impl Map {
         Ok(())
     }
 
+    /// adds zone flags to all active blocks of a kernel at the walker position
+    pub fn add_kernel_zone(
+        &mut self,
+        walker: &CuteWalker,
+        kernel: &Kernel,
+        zone: Zone,
+    ) -> Result<(), &'static str> {
+        let root_pos = self.get_kernel_root(walker, kernel)?;
+        for ((kernel_x, kernel_y), kernel_active) in kernel.vector.indexed_iter() {
+            if *kernel_active {
+                self.zones[[root_pos.x + kernel_x, root_pos.y + kernel_y]] |= zone;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// returns the top left position of a kernel at the walker position
+    fn get_kernel_root(
+        &self,
+        walker: &CuteWalker,
+        kernel: &Kernel,
+    ) -> Result<Position, &'static str> {
+        // how much kernel extends position (top/left) and (bot/right)
+        let (offset, extend) = kernel.get_extends(&walker.anchor);
+
+        let exceeds_left_bound = walker.pos.x < offset.0;
+        let exceeds_upper_bound = walker.pos.y < offset.1;
+        let exceeds_right_bound = (walker.pos.x + extend.0) >= self.width;
+        let exceeds_lower_bound = (walker.pos.y + extend.1) >= self.height;
+
+        if exceeds_left_bound || exceeds_upper_bound || exceeds_right_bound || exceeds_lower_bound {
+            return Err("Kernel out of bounds");
+        }
+
+        Ok(Position::new(
+            walker.pos.x - offset.0,
+            walker.pos.y - offset.1,
+        ))
+    }
+
     fn pos_to_chunk_pos(&self, pos: Position) -> Position {
         Position::new(pos.x / self.chunk_size, pos.y / self.chunk_size)
     }
@@ -192,6 +317,14 @@ impl Map {
         pos.x < self.width && pos.y < self.height
     }
 
+    /// checks whether a square area with given radius around pos is fully in bounds
+    pub fn square_in_bounds(&self, pos: &Position, radius: usize) -> bool {
+        pos.x >= radius
+            && pos.y >= radius
+            && pos.x + radius < self.width
+            && pos.y + radius < self.height
+    }
+
     pub fn check_area_exists(
         &self,
         top_left: &Position,
@@ -269,6 +402,82 @@ impl Map {
         }
     }
 
+    /// adds zone flags to all blocks in an area
+    pub fn add_zone(&mut self, top_left: &Position, bot_right: &Position, zone: Zone) {
+        if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
+            return;
+        }
+
+        self.zones
+            .slice_mut(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y])
+            .map_inplace(|zones| *zones |= zone);
+    }
+
+    /// adds zone flags to the outline of an area define by two positions
+    pub fn add_zone_border(&mut self, top_left: &Position, bot_right: &Position, zone: Zone) {
+        let top_right = Position::new(bot_right.x, top_left.y);
+        let bot_left = Position::new(top_left.x, bot_right.y);
+
+        self.add_zone(top_left, &top_right, zone);
+        self.add_zone(&top_right, bot_right, zone);
+        self.add_zone(top_left, &bot_left, zone);
+        self.add_zone(&bot_left, bot_right, zone);
+    }
+
+    /// checks whether any block in an area has any of the given zone flags
+    pub fn check_zone_exists(
+        &self,
+        top_left: &Position,
+        bot_right: &Position,
+        zone: Zone,
+    ) -> Result<bool, &'static str> {
+        if !self.pos_in_bounds(top_left) || !self.pos_in_bounds(bot_right) {
+            return Err("checking area out of bounds");
+        }
+
+        let area = self
+            .zones
+            .slice(s![top_left.x..=bot_right.x, top_left.y..=bot_right.y]);
+
+        Ok(area.iter().any(|zones| zones.intersects(zone)))
+    }
+
+    /// checks whether a block is empty and not protected by any of the given zone flags
+    pub fn is_free(&self, pos: &Position, zone: Zone) -> bool {
+        self.grid[pos.as_index()] == BlockType::Empty
+            && !self.zones[pos.as_index()].intersects(zone)
+    }
+
+    /// places a game tile in the front layer
+    pub fn set_front(&mut self, pos: &Position, block_type: BlockType) {
+        let (width, height) = (self.width, self.height);
+        set_layer_tile(&mut self.front, width, height, pos, block_type);
+    }
+
+    /// places a tile in the tele layer
+    pub fn set_tele(&mut self, pos: &Position, tile: TeleTile) {
+        let (width, height) = (self.width, self.height);
+        set_layer_tile(&mut self.tele, width, height, pos, tile);
+    }
+
+    /// places a tile in the speedup layer
+    pub fn set_speedup(&mut self, pos: &Position, tile: SpeedupTile) {
+        let (width, height) = (self.width, self.height);
+        set_layer_tile(&mut self.speedup, width, height, pos, tile);
+    }
+
+    /// places a tile in the switch layer
+    pub fn set_switch(&mut self, pos: &Position, tile: SwitchTile) {
+        let (width, height) = (self.width, self.height);
+        set_layer_tile(&mut self.switch, width, height, pos, tile);
+    }
+
+    /// places a tile in the tune layer
+    pub fn set_tune(&mut self, pos: &Position, tile: TuneTile) {
+        let (width, height) = (self.width, self.height);
+        set_layer_tile(&mut self.tune, width, height, pos, tile);
+    }
+
     /// sets the outline of an area define by two positions
     pub fn set_area_border(
         &mut self,
@@ -286,3 +495,115 @@ impl Map {
         self.set_area(&bot_left, bot_right, value, overwrite);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::MapConfig;
+    use crate::kernel::KernelAnchor;
+    use ndarray::s;
+
+    const MAP_SIZE: usize = 21;
+    const MAX_KERNEL_SIZE: usize = 9;
+    const CIRCULARITIES: [f32; 4] = [0.0, 0.3, 0.6, 1.0];
+    const ANCHORS: [KernelAnchor; 4] = [
+        KernelAnchor {
+            right: false,
+            down: false,
+        },
+        KernelAnchor {
+            right: true,
+            down: false,
+        },
+        KernelAnchor {
+            right: false,
+            down: true,
+        },
+        KernelAnchor {
+            right: true,
+            down: true,
+        },
+    ];
+
+    /// carves a single kernel into a solid map and returns which blocks have been carved
+    fn carve(kernel: &Kernel, anchor: KernelAnchor) -> Array2<bool> {
+        let mut map = Map::new(MAP_SIZE, MAP_SIZE, BlockType::Hookable);
+        let mut walker = CuteWalker::new(
+            Position::new(MAP_SIZE / 2, MAP_SIZE / 2),
+            kernel.clone(),
+            kernel.clone(),
+            &MapConfig::default(),
+        );
+        walker.anchor = anchor;
+
+        map.apply_kernel(&walker, kernel, BlockType::Empty)
+            .expect("kernel out of bounds");
+
+        map.grid.map(|block_type| *block_type == BlockType::Empty)
+    }
+
+    /// calls check for all tested kernel sizes, circularities and anchors
+    fn for_all_kernels(check: impl Fn(&Kernel, KernelAnchor)) {
+        for size in 1..=MAX_KERNEL_SIZE {
+            for circularity in CIRCULARITIES {
+                let kernel = Kernel::new(size, circularity);
+                for anchor in ANCHORS {
+                    check(&kernel, anchor);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn carved_area_matches_mask() {
+        for_all_kernels(|kernel, anchor| {
+            let carved = carve(kernel, anchor);
+            let (offset, _) = kernel.get_extends(&anchor);
+            let root = Position::new(MAP_SIZE / 2 - offset.0, MAP_SIZE / 2 - offset.1);
+
+            let mut expected = Array2::from_elem((MAP_SIZE, MAP_SIZE), false);
+            expected
+                .slice_mut(s![
+                    root.x..root.x + kernel.size,
+                    root.y..root.y + kernel.size
+                ])
+                .assign(&kernel.vector);
+
+            assert_eq!(carved, expected, "carved area != mask for {:?}", kernel);
+        });
+    }
+
+    #[test]
+    fn carved_area_centered_on_anchor() {
+        for_all_kernels(|kernel, anchor| {
+            let carved = carve(kernel, anchor);
+            let (offset_x, offset_y) = anchor.center_offset(kernel.size);
+            let center_x = (MAP_SIZE / 2) as f32 + 0.5 + offset_x;
+            let center_y = (MAP_SIZE / 2) as f32 + 0.5 + offset_y;
+
+            // point reflection of every carved block at the kernel center must also be carved
+            for ((x, y), active) in carved.indexed_iter() {
+                if *active {
+                    let mirror_x = (2.0 * center_x - x as f32 - 1.0) as usize;
+                    let mirror_y = (2.0 * center_y - y as f32 - 1.0) as usize;
+                    assert!(
+                        carved[[mirror_x, mirror_y]],
+                        "carved area not centered on anchor for {:?}",
+                        kernel
+                    );
+                }
+            }
+        });
+    }
+
+    #[test]
+    fn opposite_anchors_carve_mirror_images() {
+        for_all_kernels(|kernel, _| {
+            let top_left = carve(kernel, ANCHORS[0]);
+            let bot_right = carve(kernel, ANCHORS[3]);
+
+            let mirrored = bot_right.slice(s![..;-1, ..;-1]);
+            assert_eq!(top_left, mirrored, "anchors not mirrored for {:?}", kernel);
+        });
+    }
+}
@@ -0,0 +1,383 @@
+//! Migration of serialized GenerationConfigs from older versions. Configs are migrated on the
+//! json level, each step rewrites the fields of one version into the next version. This
+//! replaces typed structs for each version, so only GenerationConfig of the current version
+//! exists. Any change to the meaning of existing fields requires a new version, so that
+//! configs saved before the change are not reinterpreted.
+
+use crate::config::GenerationConfig;
+use serde_json::{json, Map, Value};
+
+/// current GenerationConfig version, configs of older versions are migrated on load
+pub const CURRENT_VERSION: &str = "1.2";
+
+/// Known GenerationConfig versions. Configs before 1.0 don't have a version field, so their
+/// version is detected based on their fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ConfigVersion {
+    /// oldest format (see old/*.json) with size bounds, step weights and waypoints
+    Legacy,
+
+    /// probabilities as lists of (value, probability) pairs
+    PairLists,
+
+    /// RandomDistConfig based probabilities, single skip config
+    V1_0,
+
+    /// separate skip configs for skips and freeze skips
+    V1_1,
+
+    /// inclusive min skip length, max skip density and self avoidance retreats
+    V1_2,
+}
+
+impl ConfigVersion {
+    pub fn detect(config: &Map<String, Value>) -> Result<ConfigVersion, &'static str> {
+        match config.get("version") {
+            Some(Value::String(version)) => match version.as_str() {
+                "1.0" => Ok(ConfigVersion::V1_0),
+                "1.1" => Ok(ConfigVersion::V1_1),
+                "1.2" => Ok(ConfigVersion::V1_2),
+                _ => Err("unsupported config version"),
+            },
+            Some(_) => Err("config version must be a string"),
+            None if config.contains_key("step_weights")
+                || config.contains_key("inner_size_bounds") =>
+            {
+                Ok(ConfigVersion::Legacy)
+            }
+            None if config.get("inner_size_probs").is_some_and(Value::is_array) => {
+                Ok(ConfigVersion::PairLists)
+            }
+            None => Ok(ConfigVersion::V1_0),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConfigVersion::Legacy => "legacy",
+            ConfigVersion::PairLists => "pre 1.0",
+            ConfigVersion::V1_0 => "1.0",
+            ConfigVersion::V1_1 => "1.1",
+            ConfigVersion::V1_2 => "1.2",
+        }
+    }
+}
+
+/// Result of a config migration. The config is in the current version, but may still contain
+/// unknown fields, which are reported as warnings.
+#[derive(Debug, Clone)]
+pub struct Migration {
+    pub config: Value,
+    pub from_version: ConfigVersion,
+    pub warnings: Vec<String>,
+}
+
+impl Migration {
+    pub fn is_upgrade(&self) -> bool {
+        self.from_version != ConfigVersion::V1_2
+    }
+}
+
+/// RandomDistConfig with uniform probabilities for all values
+fn uniform_dist(values: Vec<Value>) -> Value {
+    let prob = 1.0 / values.len().max(1) as f64;
+    json!({ "probs": vec![prob; values.len()], "values": values })
+}
+
+/// reads a (min, max) pair of integers
+fn get_bounds(config: &Map<String, Value>, key: &str) -> Result<(u64, u64), &'static str> {
+    let bounds = config.get(key).and_then(Value::as_array);
+    match bounds.map(|bounds| bounds.iter().map(Value::as_u64).collect::<Vec<_>>()) {
+        Some(bounds) if bounds.len() == 2 && bounds.iter().all(Option::is_some) => {
+            let (min, max) = (bounds[0].unwrap(), bounds[1].unwrap());
+            Ok((u64::min(min, max), u64::max(min, max)))
+        }
+        _ => Err("legacy size bounds must be a (min, max) pair"),
+    }
+}
+
+/// Legacy -> 1.0: size bounds become uniform size/margin distributions, step weights become
+/// normalized shift weights and waypoints are dropped as they are part of MapConfig now.
+fn migrate_legacy(
+    config: &mut Map<String, Value>,
+    warnings: &mut Vec<String>,
+) -> Result<(), &'static str> {
+    if let Some(step_weights) = config.remove("step_weights") {
+        let weights: Vec<f64> = step_weights
+            .as_array()
+            .ok_or("step_weights must be a list")?
+            .iter()
+            .map(|weight| weight.as_f64().ok_or("step weights must be numbers"))
+            .collect::<Result<_, _>>()?;
+        let sum: f64 = weights.iter().sum();
+        if sum <= 0.0 {
+            return Err("step weights must not sum up to zero");
+        }
+        let probs: Vec<f64> = weights.iter().map(|weight| weight / sum).collect();
+        config.insert(
+            "shift_weights".to_string(),
+            json!({ "probs": probs, "values": null }),
+        );
+    }
+
+    if config.contains_key("inner_size_bounds") {
+        let (inner_min, inner_max) = get_bounds(config, "inner_size_bounds")?;
+        let (outer_min, outer_max) = get_bounds(config, "outer_size_bounds")?;
+        config.remove("inner_size_bounds");
+        config.remove("outer_size_bounds");
+
+        let inner_sizes = (inner_min..=inner_max).map(Value::from).collect();
+        let outer_margins = (outer_min.saturating_sub(inner_max)
+            ..=outer_max.saturating_sub(inner_min))
+            .map(Value::from)
+            .collect();
+        config.insert("inner_size_probs".to_string(), uniform_dist(inner_sizes));
+        config.insert(
+            "outer_margin_probs".to_string(),
+            uniform_dist(outer_margins),
+        );
+        warnings.push(
+            "legacy size bounds converted to uniform inner size and outer margin probabilities"
+                .to_string(),
+        );
+    }
+
+    if config.remove("waypoints").is_some() {
+        warnings.push("waypoints dropped, they are part of the map config now".to_string());
+    }
+
+    Ok(())
+}
+
+/// PairLists -> 1.0: lists of (value, probability) pairs become RandomDistConfigs and shift
+/// weights are normalized
+fn migrate_pair_lists(config: &mut Map<String, Value>) -> Result<(), &'static str> {
+    for key in ["inner_size_probs", "outer_margin_probs", "circ_probs"] {
+        let pairs = match config.get(key) {
+            Some(Value::Array(pairs)) => pairs,
+            _ => continue,
+        };
+
+        let mut values = Vec::new();
+        let mut probs = Vec::new();
+        for pair in pairs {
+            match pair.as_array().map(Vec::as_slice) {
+                Some([value, prob]) => {
+                    values.push(value.clone());
+                    probs.push(prob.clone());
+                }
+                _ => return Err("probabilities must be (value, probability) pairs"),
+            }
+        }
+        config.insert(key.to_string(), json!({ "probs": probs, "values": values }));
+    }
+
+    if let Some(Value::Array(weights)) = config.get("shift_weights") {
+        let weights: Vec<f64> = weights
+            .iter()
+            .map(|weight| weight.as_f64().ok_or("shift weights must be numbers"))
+            .collect::<Result<_, _>>()?;
+        let sum: f64 = weights.iter().sum();
+        if sum <= 0.0 {
+            return Err("shift weights must not sum up to zero");
+        }
+        let probs: Vec<f64> = weights.iter().map(|weight| weight / sum).collect();
+        config.insert(
+            "shift_weights".to_string(),
+            json!({ "probs": probs, "values": null }),
+        );
+    }
+
+    Ok(())
+}
+
+/// 1.0 -> 1.1: the single skip config is used for both skips and freeze skips
+fn migrate_1_0(config: &mut Map<String, Value>) {
+    let length_bounds = config.remove("skip_length_bounds");
+    let min_spacing_sqr = config.remove("skip_min_spacing_sqr");
+
+    if length_bounds.is_some() || min_spacing_sqr.is_some() {
+        let mut skip_config = Map::new();
+        if let Some(length_bounds) = length_bounds {
+            skip_config.insert("length_bounds".to_string(), length_bounds);
+        }
+        if let Some(min_spacing_sqr) = min_spacing_sqr {
+            skip_config.insert("min_spacing_sqr".to_string(), min_spacing_sqr);
+        }
+
+        config.insert("skips".to_string(), Value::Object(skip_config.clone()));
+        config.insert("freeze_skips".to_string(), Value::Object(skip_config));
+    }
+}
+
+/// renames a field, if it exists
+fn rename_field(config: &mut Map<String, Value>, old_key: &str, new_key: &str) {
+    if let Some(value) = config.remove(old_key) {
+        config.insert(new_key.to_string(), value);
+    }
+}
+
+/// 1.1 -> 1.2: the min skip length was exclusive before and is inclusive now. The skip density
+/// is a maximum instead of a target and self avoidance backtracks are called retreats.
+fn migrate_1_1(config: &mut Map<String, Value>, warnings: &mut Vec<String>) {
+    for key in ["skips", "freeze_skips"] {
+        let min_length = config
+            .get_mut(key)
+            .and_then(|skip_config| skip_config.get_mut("length_bounds"))
+            .and_then(Value::as_array_mut)
+            .and_then(|bounds| bounds.first_mut());
+        if let Some(min_length) = min_length {
+            if let Some(min) = min_length.as_u64() {
+                *min_length = Value::from(min + 1);
+            }
+        }
+    }
+
+    if let Some(skip_density) = config.remove("skip_density") {
+        if skip_density.as_f64().is_some_and(|density| density > 0.0) {
+            warnings.push("skip_density is used as max_skip_density now".to_string());
+        }
+        config.insert("max_skip_density".to_string(), skip_density);
+    }
+
+    rename_field(
+        config,
+        "self_avoidance_backtrack_steps",
+        "self_avoidance_retreat_steps",
+    );
+    rename_field(
+        config,
+        "self_avoidance_max_backtracks",
+        "self_avoidance_max_retreats",
+    );
+    if let Some(strategy) = config.get_mut("self_avoidance_strategy") {
+        if strategy.as_str() == Some("Backtrack") {
+            *strategy = Value::from("Retreat");
+        }
+    }
+}
+
+/// Collects the paths of all fields in config that don't exist in reference. Only objects are
+/// compared recursively, as other values (e.g. probabilities) have no fixed fields.
+pub fn find_unknown_fields(
+    config: &Value,
+    reference: &Value,
+    path: &str,
+    unknown: &mut Vec<String>,
+) {
+    if let (Value::Object(config), Value::Object(reference)) = (config, reference) {
+        for (key, value) in config.iter() {
+            let field_path = match path {
+                "" => key.clone(),
+                _ => format!("{}.{}", path, key),
+            };
+
+            match reference.get(key) {
+                Some(reference_value) => {
+                    find_unknown_fields(value, reference_value, &field_path, unknown)
+                }
+                None => unknown.push(field_path),
+            }
+        }
+    }
+}
+
+/// Migrates a serialized GenerationConfig of any known version to the current version.
+/// Unknown fields are kept, but reported as warnings.
+pub fn migrate_gen_config(mut config: Value) -> Result<Migration, &'static str> {
+    let fields = config
+        .as_object_mut()
+        .ok_or("config must be a json object")?;
+    let from_version = ConfigVersion::detect(fields)?;
+    let mut warnings = Vec::new();
+
+    if from_version == ConfigVersion::Legacy {
+        migrate_legacy(fields, &mut warnings)?;
+    }
+    if from_version <= ConfigVersion::PairLists {
+        migrate_pair_lists(fields)?;
+    }
+    if from_version <= ConfigVersion::V1_0 {
+        migrate_1_0(fields);
+    }
+    if from_version <= ConfigVersion::V1_1 {
+        migrate_1_1(fields, &mut warnings);
+    }
+    fields.insert("version".to_string(), Value::from(CURRENT_VERSION));
+
+    let reference = serde_json::to_value(GenerationConfig::default()).unwrap();
+    let mut unknown = Vec::new();
+    find_unknown_fields(&config, &reference, "", &mut unknown);
+    warnings.extend(
+        unknown
+            .into_iter()
+            .map(|field| format!("unknown field '{}' is ignored", field)),
+    );
+
+    Ok(Migration {
+        config,
+        from_version,
+        warnings,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn migrated_skips(config: Value) -> (Value, Value) {
+        let migration = migrate_gen_config(config).unwrap();
+        let config: GenerationConfig = serde_json::from_value(migration.config).unwrap();
+        (
+            json!(config.skips.length_bounds),
+            json!(config.freeze_skips.length_bounds),
+        )
+    }
+
+    #[test]
+    fn min_skip_length_becomes_inclusive_once() {
+        let v1_0 = json!({ "version": "1.0", "skip_length_bounds": [3, 11] });
+        assert_eq!(migrated_skips(v1_0), (json!([4, 11]), json!([4, 11])));
+
+        let v1_1 = json!({
+            "version": "1.1",
+            "skips": { "length_bounds": [3, 11] },
+            "freeze_skips": { "length_bounds": [5, 9] },
+        });
+        assert_eq!(migrated_skips(v1_1), (json!([4, 11]), json!([6, 9])));
+
+        let v1_2 = json!({
+            "version": "1.2",
+            "skips": { "length_bounds": [4, 11] },
+            "freeze_skips": { "length_bounds": [6, 9] },
+        });
+        assert_eq!(migrated_skips(v1_2), (json!([4, 11]), json!([6, 9])));
+    }
+
+    #[test]
+    fn renamed_fields_are_migrated() {
+        let v1_1 = json!({
+            "version": "1.1",
+            "skip_density": 2.0,
+            "self_avoidance_strategy": "Backtrack",
+            "self_avoidance_backtrack_steps": 7,
+            "self_avoidance_max_backtracks": 3,
+        });
+        let migration = migrate_gen_config(v1_1).unwrap();
+        assert_eq!(migration.from_version, ConfigVersion::V1_1);
+        assert!(!migration
+            .warnings
+            .iter()
+            .any(|warning| warning.starts_with("unknown field")));
+
+        let config: GenerationConfig = serde_json::from_value(migration.config).unwrap();
+        assert_eq!(config.version, CURRENT_VERSION);
+        assert_eq!(config.max_skip_density, 2.0);
+        assert_eq!(
+            config.self_avoidance_strategy,
+            crate::config::SelfAvoidanceStrategy::Retreat
+        );
+        assert_eq!(config.self_avoidance_retreat_steps, 7);
+        assert_eq!(config.self_avoidance_max_retreats, 3);
+    }
+}
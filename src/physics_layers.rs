@@ -0,0 +1,145 @@
+use crate::position::ShiftDirection;
+use ndarray::Array2;
+
+/// tele tile types, see DDNet's tele layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TeleType {
+    /// teleports to a random tele out with the same number
+    In,
+
+    /// like In, but also resets speed and hook
+    EvilIn,
+
+    /// target of In and EvilIn
+    Out,
+
+    /// sets the current checkpoint number
+    Checkpoint,
+
+    /// teleports to the checkpoint out matching the last checkpoint
+    CheckpointIn,
+
+    /// like CheckpointIn, but also resets speed and hook
+    EvilCheckpointIn,
+
+    /// target of CheckpointIn and EvilCheckpointIn
+    CheckpointOut,
+}
+
+impl TeleType {
+    /// maps TeleType to tw tele layer id for map export
+    pub fn to_tw_tele_id(&self) -> u8 {
+        match self {
+            TeleType::In => 26,
+            TeleType::EvilIn => 10,
+            TeleType::Out => 27,
+            TeleType::Checkpoint => 29,
+            TeleType::CheckpointIn => 31,
+            TeleType::EvilCheckpointIn => 63,
+            TeleType::CheckpointOut => 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TeleTile {
+    pub tele_type: TeleType,
+
+    /// tele number connecting ins and outs
+    pub number: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpeedupTile {
+    /// acceleration applied to tees on the tile
+    pub force: u8,
+
+    /// max speed up to which tees are accelerated, 0 for no limit
+    pub max_speed: u8,
+
+    /// direction of the speedup in degrees, 0 is pointing right
+    pub angle: i16,
+}
+
+impl SpeedupTile {
+    /// tw speedup layer id for map export
+    pub const TW_SPEEDUP_ID: u8 = 28;
+
+    /// speedup pointing into the given direction. The base force is doubled upwards and
+    /// halved downwards to account for gravity.
+    pub fn in_direction(dir: ShiftDirection, force: u8, max_speed: u8) -> SpeedupTile {
+        let (angle, force) = match dir {
+            ShiftDirection::Right => (0, force),
+            ShiftDirection::Down => (90, force / 2),
+            ShiftDirection::Left => (180, force),
+            ShiftDirection::Up => (270, force.saturating_mul(2)),
+        };
+
+        SpeedupTile {
+            force,
+            max_speed,
+            angle,
+        }
+    }
+}
+
+/// switch tile types, see DDNet's switch layer
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SwitchType {
+    /// door which is closed until its switch number is activated
+    Door,
+
+    /// activates the switch number
+    Open,
+
+    /// deactivates the switch number
+    Close,
+
+    /// activates the switch number for the given delay in seconds
+    TimedOpen,
+
+    /// deactivates the switch number for the given delay in seconds
+    TimedClose,
+
+    /// freeze that is only active while its switch number is active
+    Freeze,
+}
+
+impl SwitchType {
+    /// maps SwitchType to tw switch layer id for map export
+    pub fn to_tw_switch_id(&self) -> u8 {
+        match self {
+            SwitchType::Door => 240,
+            SwitchType::Open => 24,
+            SwitchType::Close => 25,
+            SwitchType::TimedOpen => 22,
+            SwitchType::TimedClose => 23,
+            SwitchType::Freeze => 9,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SwitchTile {
+    pub switch_type: SwitchType,
+
+    /// switch number connecting switches and their targets
+    pub number: u8,
+
+    /// delay in seconds for timed switches
+    pub delay: u8,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TuneTile {
+    /// number of the tune zone, settings are defined via map commands
+    pub number: u8,
+}
+
+impl TuneTile {
+    /// tw tune layer id for map export
+    pub const TW_TUNE_ID: u8 = 68;
+}
+
+/// Additional DDNet physics layer with typed tiles. None tiles are left empty on export.
+pub type PhysicsLayer<T> = Array2<Option<T>>;
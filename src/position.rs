@@ -22,6 +22,28 @@ pub enum ShiftDirection {
     Left = 3,
 }
 
+impl ShiftDirection {
+    /// returns the direction rotated by 90 degrees clockwise
+    pub fn rotated_cw(&self) -> ShiftDirection {
+        match self {
+            ShiftDirection::Up => ShiftDirection::Right,
+            ShiftDirection::Right => ShiftDirection::Down,
+            ShiftDirection::Down => ShiftDirection::Left,
+            ShiftDirection::Left => ShiftDirection::Up,
+        }
+    }
+
+    /// returns the (x, y) offset of a single step into this direction
+    pub fn offset(&self) -> (i32, i32) {
+        match self {
+            ShiftDirection::Up => (0, -1),
+            ShiftDirection::Right => (1, 0),
+            ShiftDirection::Down => (0, 1),
+            ShiftDirection::Left => (-1, 0),
+        }
+    }
+}
+
 impl Position {
     pub fn new(x: usize, y: usize) -> Position {
         Position { x, y }
@@ -1,13 +1,17 @@
 use crate::{
+    config::SkipConfig,
+    corner_shape::find_corner_candidates,
     generator::Generator,
-    map::{BlockType, Overwrite},
+    map::{BlockType, Overwrite, Zone},
+    physics_layers::{SpeedupTile, TeleTile, TeleType},
     position::{Position, ShiftDirection},
 };
 
-use std::{f32::consts::SQRT_2, usize};
+use std::{collections::VecDeque, f32::consts::SQRT_2, usize};
 
 use dt::dt_bool;
 use ndarray::{s, Array2, ArrayBase, Dim, Ix2, ViewRepr};
+use noise::{NoiseFn, Perlin};
 
 pub fn is_freeze(block_type: &&BlockType) -> bool {
     **block_type == BlockType::Freeze
@@ -22,8 +26,7 @@ pub fn fix_edge_bugs(gen: &mut Generator) -> Result<Array2<bool>, &'static str>
 
     for x in 0..width {
         for y in 0..height {
-            let value = &gen.map.grid[[x, y]];
-            if *value == BlockType::Empty {
+            if gen.map.is_free(&Position::new(x, y), Zone::PROTECTED) {
                 for dx in 0..=2 {
                     for dy in 0..=2 {
                         if dx == 1 && dy == 1 {
@@ -38,7 +41,7 @@ pub fn fix_edge_bugs(gen: &mut Generator) -> Result<Array2<bool>, &'static str>
                             .ok_or("fix edge bug out of bounds")?;
                         if neighbor_x < width && neighbor_y < height {
                             let neighbor_value = &gen.map.grid[[neighbor_x, neighbor_y]];
-                            if *neighbor_value == BlockType::Hookable {
+                            if neighbor_value.is_wall() {
                                 edge_bug[[x, y]] = true;
                                 // break;
                                 // TODO: this should be easy to optimize
@@ -58,177 +61,387 @@ pub fn fix_edge_bugs(gen: &mut Generator) -> Result<Array2<bool>, &'static str>
 }
 
 /// Using a distance transform this function will fill up all empty blocks that are too far
-/// from the next solid/non-empty block
+/// from the next solid/non-empty block. Blocks in no obstacle zones are never filled.
 pub fn fill_open_areas(gen: &mut Generator, max_distance: &f32) -> Array2<f32> {
-    let grid = gen.map.grid.map(|val| *val != BlockType::Empty);
+    // protected blocks are treated as non empty
+    let grid = Array2::from_shape_fn(gen.map.grid.dim(), |(x, y)| {
+        !gen.map.is_free(&Position::new(x, y), Zone::PROTECTED)
+    });
+
+    // euclidean distance transform
+    let distance = dt_bool::<f32>(&grid.clone().into_dyn())
+        .into_dimensionality::<Ix2>()
+        .unwrap();
+
+    for ((x, y), distance) in distance.indexed_iter() {
+        // only modify empty, unprotected blocks
+        if grid[[x, y]] || gen.map.zones[[x, y]].intersects(Zone::NO_OBSTACLE) {
+            continue;
+        }
+
+        if *distance > *max_distance + SQRT_2 {
+            gen.map.grid[[x, y]] = BlockType::Hookable;
+        } else if *distance > *max_distance {
+            gen.map.grid[[x, y]] = BlockType::Freeze;
+        }
+    }
+
+    distance
+}
+
+/// Places organic freeze/hookable obstacles inside open areas. Obstacles are derived by
+/// thresholding perlin noise, but only for empty blocks that have at least min_clearance
+/// distance to the next non empty block. The threshold is chosen such that density is roughly
+/// the fraction of these blocks that become obstacles (noise values can repeat). Obstacles are
+/// outlined with freeze, so small or thin obstacles end up as freeze only. Returns which blocks
+/// have been turned into obstacles.
+pub fn generate_noise_obstacles(
+    gen: &mut Generator,
+    density: f32,
+    scale: f32,
+    min_clearance: f32,
+) -> Array2<bool> {
+    // protected blocks are treated as non empty
+    let grid = Array2::from_shape_fn(gen.map.grid.dim(), |(x, y)| {
+        !gen.map.is_free(&Position::new(x, y), Zone::PROTECTED)
+    });
 
     // euclidean distance transform
     let distance = dt_bool::<f32>(&grid.into_dyn())
         .into_dimensionality::<Ix2>()
         .unwrap();
 
-    gen.map
-        .grid
-        .zip_mut_with(&distance, |block_type, distance| {
-            // only modify empty blocks
-            if *block_type != BlockType::Empty {
-                return;
+    let perlin = Perlin::new(gen.rnd.random_u64() as u32);
+
+    // noise of all blocks that can become obstacles
+    let noise = Array2::from_shape_fn(gen.map.grid.dim(), |(x, y)| {
+        if !gen
+            .map
+            .is_free(&Position::new(x, y), Zone::PROTECTED | Zone::NO_OBSTACLE)
+            || distance[[x, y]] < min_clearance
+        {
+            return None;
+        }
+
+        Some(perlin.get([x as f64 / scale as f64, y as f64 / scale as f64]))
+    });
+
+    // perlin noise doesn't cover its range uniformly, so the threshold is the quantile of the
+    // sampled noise values that results in the requested density. Blocks with the same noise
+    // as the threshold all become obstacles, so the density can be slightly exceeded.
+    let mut values: Vec<f64> = noise.iter().flatten().copied().collect();
+    let obstacle_count = (values.len() as f32 * density.clamp(0.0, 1.0)).round() as usize;
+    let threshold = match obstacle_count {
+        0 => f64::INFINITY,
+        _ => {
+            let index = values.len() - obstacle_count;
+            *values.select_nth_unstable_by(index, f64::total_cmp).1
+        }
+    };
+
+    let obstacles = noise.map(|noise| noise.is_some_and(|noise| noise >= threshold));
+
+    let width = gen.map.width;
+    let height = gen.map.height;
+    for ((x, y), obstacle) in obstacles.indexed_iter() {
+        if !obstacle {
+            continue;
+        }
+
+        // only blocks that are fully surrounded by obstacle blocks become hookable
+        let is_core = x > 0
+            && y > 0
+            && x < width - 1
+            && y < height - 1
+            && get_window(&obstacles, x, y, 1).iter().all(|val| *val);
+
+        gen.map.grid[[x, y]] = match is_core {
+            true => BlockType::Hookable,
+            false => BlockType::Freeze,
+        };
+    }
+
+    obstacles
+}
+
+/// Tele number of checkpoint trap tiles. DDNet ignores tele tiles with number 0, but the
+/// target is chosen by the last reached checkpoint, so any other number works.
+pub const CHECKPOINT_TRAP_TELE_NUMBER: u8 = 1;
+
+/// Turns horizontal freeze floors with at least min_length blocks into traps with the given
+/// probability. Trap blocks are covered by checkpoint tele ins, so falling into them teleports
+/// tees back to their last checkpoint, or to spawn if no checkpoint was reached yet.
+pub fn generate_checkpoint_traps(
+    gen: &mut Generator,
+    min_length: usize,
+    prob: f32,
+) -> Array2<bool> {
+    let mut traps = Array2::from_elem(gen.map.grid.dim(), false);
+
+    let is_floor = |gen: &Generator, x: usize, y: usize| {
+        gen.map.grid[[x, y]] == BlockType::Freeze
+            && gen.map.grid[[x, y - 1]] == BlockType::Empty
+            && !gen.map.zones[[x, y]].intersects(Zone::PROTECTED)
+            && gen
+                .map
+                .tele
+                .as_ref()
+                .and_then(|tele| tele[[x, y]])
+                .is_none()
+    };
+
+    for y in 1..gen.map.height {
+        let mut x = 0;
+        while x < gen.map.width {
+            let run_start = x;
+            while x < gen.map.width && is_floor(gen, x, y) {
+                x += 1;
             }
 
-            if *distance > *max_distance + SQRT_2 {
-                *block_type = BlockType::Hookable;
-            } else if *distance > *max_distance {
-                *block_type = BlockType::Freeze;
+            if x - run_start >= min_length && gen.rnd.with_probability(prob) {
+                for trap_x in run_start..x {
+                    traps[[trap_x, y]] = true;
+                    gen.map.set_tele(
+                        &Position::new(trap_x, y),
+                        TeleTile {
+                            tele_type: TeleType::CheckpointIn,
+                            number: CHECKPOINT_TRAP_TELE_NUMBER,
+                        },
+                    );
+                }
             }
-        });
 
-    distance
-}
+            x += 1;
+        }
+    }
 
-// returns a vec of corner candidates and their respective direction to the wall
-pub fn find_corners(gen: &Generator) -> Result<Vec<(Position, ShiftDirection)>, &'static str> {
-    let mut candidates: Vec<(Position, ShiftDirection)> = Vec::new();
+    traps
+}
 
+/// Converts segments of wall surface into unhookable walls. Each hookable block next to an
+/// empty or freeze block starts a new segment with the given probability. Segments grow along
+/// the wall surface until their sampled length is reached. Returns the converted blocks.
+pub fn generate_unhookable_walls(
+    gen: &mut Generator,
+    prob: f32,
+    length_bounds: (usize, usize),
+) -> Array2<bool> {
     let width = gen.map.width;
     let height = gen.map.height;
 
-    let window_size = 2; // 2 -> 5x5 windows
+    let is_surface = |gen: &Generator, x: usize, y: usize| {
+        gen.map.grid[[x, y]] == BlockType::Hookable
+            && !gen.map.zones[[x, y]].intersects(Zone::PROTECTED)
+            && [(-1, 0), (1, 0), (0, -1), (0, 1)].iter().any(|(dx, dy)| {
+                Position::new(x, y)
+                    .shifted_by(*dx, *dy)
+                    .ok()
+                    .and_then(|pos| gen.map.grid.get(pos.as_index()))
+                    .is_some_and(|block| matches!(block, BlockType::Empty | BlockType::Freeze))
+            })
+    };
 
-    for window_x in window_size..(width - window_size) {
-        for window_y in window_size..(height - window_size) {
-            let window = &gen.map.grid.slice(s![
-                window_x - window_size..=window_x + window_size,
-                window_y - window_size..=window_y + window_size
-            ]);
+    let mut unhookable = Array2::from_elem((width, height), false);
 
-            if window[[2, 2]] != BlockType::Empty {
+    for x in 0..width {
+        for y in 0..height {
+            if !is_surface(gen, x, y) || !gen.rnd.with_probability(prob) {
                 continue;
             }
 
-            let shapes = [
-                // R1
-                (
-                    [
-                        &window[[2, 3]],
-                        &window[[3, 0]],
-                        &window[[3, 1]],
-                        &window[[3, 2]],
-                        &window[[3, 3]],
-                    ],
-                    ShiftDirection::Right,
-                ),
-                // R2
-                (
-                    [
-                        &window[[2, 1]],
-                        &window[[3, 1]],
-                        &window[[3, 2]],
-                        &window[[3, 3]],
-                        &window[[3, 4]],
-                    ],
-                    ShiftDirection::Right,
-                ),
-                // L1
-                (
-                    [
-                        &window[[2, 3]],
-                        &window[[1, 0]],
-                        &window[[1, 1]],
-                        &window[[1, 2]],
-                        &window[[1, 3]],
-                    ],
-                    ShiftDirection::Left,
-                ),
-                // L2
-                (
-                    [
-                        &window[[2, 1]],
-                        &window[[1, 1]],
-                        &window[[1, 2]],
-                        &window[[1, 3]],
-                        &window[[1, 4]],
-                    ],
-                    ShiftDirection::Left,
-                ),
-                // U1
-                (
-                    [
-                        &window[[3, 2]],
-                        &window[[0, 1]],
-                        &window[[1, 1]],
-                        &window[[2, 1]],
-                        &window[[3, 1]],
-                    ],
-                    ShiftDirection::Up,
-                ),
-                // U2
-                (
-                    [
-                        &window[[1, 2]],
-                        &window[[1, 1]],
-                        &window[[2, 1]],
-                        &window[[3, 1]],
-                        &window[[4, 1]],
-                    ],
-                    ShiftDirection::Up,
-                ),
-                // D1
-                (
-                    [
-                        &window[[3, 2]],
-                        &window[[0, 3]],
-                        &window[[1, 3]],
-                        &window[[2, 3]],
-                        &window[[3, 3]],
-                    ],
-                    ShiftDirection::Down,
-                ),
-                // D2
-                (
-                    [
-                        &window[[1, 2]],
-                        &window[[1, 3]],
-                        &window[[2, 3]],
-                        &window[[3, 3]],
-                        &window[[4, 3]],
-                    ],
-                    ShiftDirection::Down,
-                ),
-            ];
-
-            for (shape, dir) in shapes {
-                if shape.iter().all(is_freeze) {
-                    candidates.push((Position::new(window_x, window_y), dir));
+            let length = gen.rnd.in_range_inclusive(length_bounds.0, length_bounds.1);
+            let mut segment_length = 0;
+            let mut visit_next = VecDeque::from([Position::new(x, y)]);
+            while let Some(pos) = visit_next.pop_front() {
+                if segment_length >= length {
+                    break;
+                }
+                if !is_surface(gen, pos.x, pos.y) {
+                    continue;
+                }
+
+                gen.map.grid[pos.as_index()] = BlockType::Unhookable;
+                unhookable[pos.as_index()] = true;
+                segment_length += 1;
+
+                // grow along connected wall surface
+                for (dx, dy) in [
+                    (-1, 0),
+                    (1, 0),
+                    (0, -1),
+                    (0, 1),
+                    (-1, -1),
+                    (1, -1),
+                    (-1, 1),
+                    (1, 1),
+                ] {
+                    if let Ok(neighbor) = pos.shifted_by(dx, dy) {
+                        if gen.map.pos_in_bounds(&neighbor) {
+                            visit_next.push_back(neighbor);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    unhookable
+}
+
+/// Places speedups at the speedup candidates of the walker. For each candidate, speedup rows
+/// are placed behind the candidate position, each spanning the free blocks perpendicular to the
+/// walker direction.
+pub fn generate_speedups(
+    gen: &mut Generator,
+    length: usize,
+    force: u8,
+    max_speed: u8,
+) -> Array2<bool> {
+    let mut speedups = Array2::from_elem(gen.map.grid.dim(), false);
+
+    let is_free = |gen: &Generator, pos: &Position| {
+        gen.map.pos_in_bounds(pos) && gen.map.is_free(pos, Zone::PROTECTED)
+    };
+
+    for (pos, dir) in gen.walker.speedup_candidates.clone() {
+        let (dx, dy) = dir.offset();
+        let tile = SpeedupTile::in_direction(dir, force, max_speed);
+
+        for row in 0..length as i32 {
+            let center = match pos.shifted_by(-dx * row, -dy * row) {
+                Ok(center) if is_free(gen, &center) => center,
+                _ => break,
+            };
+
+            // extend row to both sides until a non free block is reached
+            let mut row_blocks = vec![center.clone()];
+            for side in [-1, 1] {
+                let mut dist = 1;
+                while let Ok(side_pos) = center.shifted_by(side * dy * dist, side * dx * dist) {
+                    if !is_free(gen, &side_pos) {
+                        break;
+                    }
+                    row_blocks.push(side_pos);
+                    dist += 1;
                 }
             }
+
+            for block in row_blocks {
+                speedups[block.as_index()] = true;
+                gen.map.set_speedup(&block, tile);
+            }
         }
     }
 
-    Ok(candidates)
+    speedups
+}
+
+// returns a vec of corner candidates and their respective direction to the wall
+pub fn find_corners(gen: &Generator) -> Result<Vec<(Position, ShiftDirection)>, &'static str> {
+    if gen.corner_shapes.is_empty() {
+        return Err("no corner shapes available");
+    }
+
+    Ok(find_corner_candidates(&gen.map, &gen.corner_shapes))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SkipShape {
+    /// straight cut into the wall direction
+    Straight,
+
+    /// 45 degree cut into the wall direction
+    Diagonal,
+
+    /// straight cut into the wall direction that turns by 90 degrees inside the hookable wall
+    LShaped,
 }
 
 pub struct Skip {
     start_pos: Position,
     end_pos: Position,
     length: usize,
-    direction: ShiftDirection,
+    shape: SkipShape,
+
+    /// all blocks of the skip from start to end position, each with the (x, y) direction of
+    /// the segment it belongs to
+    path: Vec<(Position, (i32, i32))>,
 }
 
-/// if a skip has been found, this returns the end position and length
-pub fn check_corner_skip(
+impl Skip {
+    /// returns the side blocks of a path block at the given distance, perpendicular to its
+    /// segment direction
+    fn get_sides(pos: &Position, dir: (i32, i32), offset: i32) -> [Option<Position>; 2] {
+        let (side_x, side_y) = (-dir.1 * offset, dir.0 * offset);
+        [
+            pos.shifted_by(side_x, side_y).ok(),
+            pos.shifted_by(-side_x, -side_y).ok(),
+        ]
+    }
+
+    /// returns (top_left, bot_right) of the area carved by the skip, including the freeze
+    /// blocks on both sides
+    pub fn get_area(&self) -> (Position, Position) {
+        let mut top_left = self.start_pos.clone();
+        let mut bot_right = self.start_pos.clone();
+
+        for (pos, dir) in self.path.iter() {
+            let sides = Skip::get_sides(pos, *dir, 1);
+            for block in sides.iter().flatten().chain([pos]) {
+                top_left.x = usize::min(top_left.x, block.x);
+                top_left.y = usize::min(top_left.y, block.y);
+                bot_right.x = usize::max(bot_right.x, block.x);
+                bot_right.y = usize::max(bot_right.y, block.y);
+            }
+        }
+
+        (top_left, bot_right)
+    }
+
+    /// checks whether the areas of two skips overlap
+    pub fn intersects(&self, other: &Skip) -> bool {
+        let (top_left, bot_right) = self.get_area();
+        let (other_top_left, other_bot_right) = other.get_area();
+
+        top_left.x <= other_bot_right.x
+            && other_top_left.x <= bot_right.x
+            && top_left.y <= other_bot_right.y
+            && other_top_left.y <= bot_right.y
+    }
+
+    /// returns the min squared distance between the ends of two skips
+    pub fn min_end_distance_squared(&self, other: &Skip) -> usize {
+        [
+            self.start_pos.distance_squared(&other.start_pos),
+            self.start_pos.distance_squared(&other.end_pos),
+            self.end_pos.distance_squared(&other.start_pos),
+            self.end_pos.distance_squared(&other.end_pos),
+        ]
+        .into_iter()
+        .min()
+        .unwrap()
+    }
+}
+
+/// follows a straight segment through the skip stages freeze -> hookable -> freeze -> empty,
+/// starting at the given stage. Returns all visited blocks with their stage, stopping at the
+/// final stage (4), at the first block not matching the sequence or after max_length blocks.
+fn trace_skip_segment(
     gen: &Generator,
     init_pos: &Position,
-    shift: &ShiftDirection,
-    tunnel_bounds: (usize, usize),
-) -> Option<Skip> {
+    init_stage: usize,
+    dir: (i32, i32),
+    max_length: usize,
+) -> Vec<(Position, usize)> {
+    let mut trace = Vec::new();
     let mut pos = init_pos.clone();
+    let mut stage = init_stage;
 
-    let mut length = 0;
-    let mut stage = 0;
-    while stage != 4 && length < tunnel_bounds.1 {
+    while stage != 4 && trace.len() < max_length {
         // shift into given direction, abort if invalid shift
-        if pos.shift_in_direction(shift, &gen.map).is_err() {
-            return None;
+        pos = match pos.shifted_by(dir.0, dir.1) {
+            Ok(pos) if gen.map.pos_in_bounds(&pos) => pos,
+            _ => break,
         };
         let curr_block_type = gen.map.grid.get(pos.as_index()).unwrap();
 
@@ -236,8 +449,8 @@ pub fn check_corner_skip(
             // proceed to / or stay in stage 1 if freeze is found
             (0 | 1, BlockType::Freeze) => 1,
 
-            // proceed to / or stay in stage 2 if hookable is found
-            (1 | 2, BlockType::Hookable) => 2,
+            // proceed to / or stay in stage 2 if a (un)hookable wall is found
+            (1 | 2, BlockType::Hookable | BlockType::Unhookable) => 2,
 
             // proceed to / or stay in stage 2 if freeze is found
             (2 | 3, BlockType::Freeze) => 3,
@@ -246,122 +459,197 @@ pub fn check_corner_skip(
             (3, BlockType::Empty) => 4,
 
             // no match -> invalid sequence, abort!
-            _ => return None,
+            _ => break,
         };
 
-        length += 1;
+        trace.push((pos.clone(), stage));
     }
 
-    if stage == 4 && length > tunnel_bounds.0 {
-        Some(Skip {
+    trace
+}
+
+/// checks for a straight skip from the corner candidate towards the wall direction
+pub fn check_corner_skip(
+    gen: &Generator,
+    init_pos: &Position,
+    shift: &ShiftDirection,
+    tunnel_bounds: (usize, usize),
+) -> Option<Skip> {
+    let dir = shift.offset();
+    let trace = trace_skip_segment(gen, init_pos, 0, dir, tunnel_bounds.1);
+
+    match trace.last() {
+        Some((end_pos, 4)) if trace.len() >= tunnel_bounds.0 => Some(Skip {
             start_pos: init_pos.clone(),
-            end_pos: pos,
-            length,
-            direction: shift.clone(),
-        })
-    } else {
-        None
+            end_pos: end_pos.clone(),
+            length: trace.len(),
+            shape: SkipShape::Straight,
+            path: [(init_pos.clone(), dir)]
+                .into_iter()
+                .chain(trace.into_iter().map(|(pos, _)| (pos, dir)))
+                .collect(),
+        }),
+        _ => None,
+    }
+}
+
+/// checks for 45 degree skips from the corner candidate, leaning to both sides of the wall
+/// direction
+pub fn check_diagonal_skips(
+    gen: &Generator,
+    init_pos: &Position,
+    shift: &ShiftDirection,
+    tunnel_bounds: (usize, usize),
+) -> Vec<Skip> {
+    let (wall_x, wall_y) = shift.offset();
+    let mut skips = Vec::new();
+
+    for (side_x, side_y) in [(-wall_y, wall_x), (wall_y, -wall_x)] {
+        let dir = (wall_x + side_x, wall_y + side_y);
+        let trace = trace_skip_segment(gen, init_pos, 0, dir, tunnel_bounds.1);
+
+        if let Some((end_pos, 4)) = trace.last() {
+            if trace.len() >= tunnel_bounds.0 {
+                skips.push(Skip {
+                    start_pos: init_pos.clone(),
+                    end_pos: end_pos.clone(),
+                    length: trace.len(),
+                    shape: SkipShape::Diagonal,
+                    path: [(init_pos.clone(), dir)]
+                        .into_iter()
+                        .chain(trace.into_iter().map(|(pos, _)| (pos, dir)))
+                        .collect(),
+                });
+            }
+        }
+    }
+
+    skips
+}
+
+/// checks for skips that enter the wall and turn by 90 degrees inside the hookable part. For
+/// each turn direction, the skip with the earliest possible turn is returned.
+pub fn check_l_skips(
+    gen: &Generator,
+    init_pos: &Position,
+    shift: &ShiftDirection,
+    tunnel_bounds: (usize, usize),
+) -> Vec<Skip> {
+    let dir = shift.offset();
+    let mut skips = Vec::new();
+
+    let first_segment = trace_skip_segment(gen, init_pos, 0, dir, tunnel_bounds.1);
+
+    for turn_dir in [(-dir.1, dir.0), (dir.1, -dir.0)] {
+        for (turn_index, (turn_pos, stage)) in first_segment.iter().enumerate() {
+            // only turn inside the hookable part of the wall
+            if *stage != 2 {
+                continue;
+            }
+
+            let first_length = turn_index + 1;
+            let second_segment =
+                trace_skip_segment(gen, turn_pos, 2, turn_dir, tunnel_bounds.1 - first_length);
+
+            let length = first_length + second_segment.len();
+            if let Some((end_pos, 4)) = second_segment.last() {
+                if length >= tunnel_bounds.0 {
+                    skips.push(Skip {
+                        start_pos: init_pos.clone(),
+                        end_pos: end_pos.clone(),
+                        length,
+                        shape: SkipShape::LShaped,
+                        path: [(init_pos.clone(), dir)]
+                            .into_iter()
+                            .chain(
+                                first_segment[..first_length]
+                                    .iter()
+                                    .map(|(pos, _)| (pos.clone(), dir)),
+                            )
+                            .chain(second_segment.into_iter().map(|(pos, _)| (pos, turn_dir)))
+                            .collect(),
+                    });
+                    break;
+                }
+            }
+        }
     }
+
+    skips
 }
 
+/// counts hookable blocks next to the skip at the given distance and returns the minimum of
+/// both sides
 pub fn count_skip_neighbours(
     gen: &mut Generator,
     skip: &Skip,
     offset: usize,
 ) -> Result<usize, &'static str> {
-    let top_left = Position::new(
-        usize::min(skip.start_pos.x, skip.end_pos.x),
-        usize::min(skip.start_pos.y, skip.end_pos.y),
-    );
-    let bot_right = Position::new(
-        usize::max(skip.start_pos.x, skip.end_pos.x),
-        usize::max(skip.start_pos.y, skip.end_pos.y),
-    );
-
-    let offset: i32 = offset as i32;
-
-    match skip.direction {
-        ShiftDirection::Left | ShiftDirection::Right => {
-            let bot_count = gen.map.count_occurence_in_area(
-                &top_left.shifted_by(0, offset)?,
-                &bot_right.shifted_by(0, offset)?,
-                &BlockType::Hookable,
-            )?;
-            let top_count = gen.map.count_occurence_in_area(
-                &top_left.shifted_by(0, -offset)?,
-                &bot_right.shifted_by(0, -offset)?,
-                &BlockType::Hookable,
-            )?;
-
-            Ok(usize::min(bot_count, top_count))
-        }
-        ShiftDirection::Up | ShiftDirection::Down => {
-            let left_count = gen.map.count_occurence_in_area(
-                &top_left.shifted_by(-offset, 0)?,
-                &bot_right.shifted_by(-offset, 0)?,
-                &BlockType::Hookable,
-            )?;
-            let right_count = gen.map.count_occurence_in_area(
-                &top_left.shifted_by(offset, 0)?,
-                &bot_right.shifted_by(offset, 0)?,
-                &BlockType::Hookable,
-            )?;
-
-            Ok(usize::min(left_count, right_count))
+    let mut counts = [0, 0];
+
+    for (pos, dir) in skip.path.iter() {
+        let sides = Skip::get_sides(pos, *dir, offset as i32);
+        for (count, side) in counts.iter_mut().zip(sides) {
+            let side = side
+                .filter(|side| gen.map.pos_in_bounds(side))
+                .ok_or("skip neighbour out of bounds")?;
+            if gen.map.grid[side.as_index()].is_wall() {
+                *count += 1;
+            }
         }
     }
+
+    Ok(usize::min(counts[0], counts[1]))
 }
 
 pub fn generate_skip(gen: &mut Generator, skip: &Skip, block_type: &BlockType) {
-    let top_left = Position::new(
-        usize::min(skip.start_pos.x, skip.end_pos.x),
-        usize::min(skip.start_pos.y, skip.end_pos.y),
-    );
-    let bot_right = Position::new(
-        usize::max(skip.start_pos.x, skip.end_pos.x),
-        usize::max(skip.start_pos.y, skip.end_pos.y),
-    );
-
-    gen.map.set_area(
-        &top_left,
-        &bot_right,
-        block_type,
-        &Overwrite::ReplaceSolidFreeze,
-    );
+    for (pos, dir) in skip.path.iter() {
+        gen.map
+            .set_area(pos, pos, block_type, &Overwrite::ReplaceSolidFreeze);
+        gen.map.add_zone(pos, pos, Zone::SKIP);
+
+        // diagonal steps are only connected via their corners, so also cut the horizontal
+        // neighbour to keep the skip passable
+        if dir.0 != 0 && dir.1 != 0 && *pos != skip.end_pos {
+            if let Ok(neighbour) = pos.shifted_by(dir.0, 0) {
+                gen.map.set_area(
+                    &neighbour,
+                    &neighbour,
+                    block_type,
+                    &Overwrite::ReplaceSolidFreeze,
+                );
+                gen.map.add_zone(&neighbour, &neighbour, Zone::SKIP);
+            }
+        }
+    }
 
     // TODO: shitty prototype
     if block_type.is_freeze() {
         return;
     }
 
-    match skip.direction {
-        ShiftDirection::Left | ShiftDirection::Right => {
-            gen.map.set_area(
-                &top_left.shifted_by(0, -1).unwrap(),
-                &bot_right.shifted_by(0, -1).unwrap(),
-                &BlockType::Freeze,
-                &Overwrite::ReplaceSolidOnly,
-            );
-            gen.map.set_area(
-                &top_left.shifted_by(0, 1).unwrap(),
-                &bot_right.shifted_by(0, 1).unwrap(),
-                &BlockType::Freeze,
-                &Overwrite::ReplaceSolidOnly,
-            );
-        }
-        ShiftDirection::Up | ShiftDirection::Down => {
-            gen.map.set_area(
-                &top_left.shifted_by(-1, 0).unwrap(),
-                &bot_right.shifted_by(-1, 0).unwrap(),
-                &BlockType::Freeze,
-                &Overwrite::ReplaceSolidOnly,
-            );
-            gen.map.set_area(
-                &top_left.shifted_by(1, 0).unwrap(),
-                &bot_right.shifted_by(1, 0).unwrap(),
-                &BlockType::Freeze,
-                &Overwrite::ReplaceSolidOnly,
-            );
+    for (pos, dir) in skip.path.iter() {
+        match skip.shape {
+            SkipShape::Straight => {
+                for side in Skip::get_sides(pos, *dir, 1).iter().flatten() {
+                    gen.map
+                        .set_area(side, side, &BlockType::Freeze, &Overwrite::ReplaceSolidOnly);
+                }
+            }
+            SkipShape::Diagonal | SkipShape::LShaped => {
+                // cover all blocks around the path, so diagonal steps and the corner of the
+                // turn are also surrounded by freeze
+                if let (Ok(top_left), Ok(bot_right)) =
+                    (pos.shifted_by(-1, -1), pos.shifted_by(1, 1))
+                {
+                    gen.map.set_area(
+                        &top_left,
+                        &bot_right,
+                        &BlockType::Freeze,
+                        &Overwrite::ReplaceSolidOnly,
+                    );
+                }
+            }
         }
     }
 }
@@ -373,64 +661,156 @@ enum SkipStatus {
     Valid,
 }
 
+/// picks skips of the given status from the candidates. Candidates are rejected if they are
+/// too close to or intersect with an already picked skip. Returns the number of picked skips.
+fn select_skips(
+    gen: &mut Generator,
+    skips: &[Skip],
+    candidates: &[SkipStatus],
+    selection: &mut [SkipStatus],
+    status: SkipStatus,
+    config: &SkipConfig,
+    max_count: usize,
+) -> usize {
+    let mut count = 0;
+
+    for skip_index in 0..skips.len() {
+        if candidates[skip_index] != status {
+            continue;
+        }
+
+        let skip = &skips[skip_index];
+
+        if skip.length < config.length_bounds.0 || skip.length > config.length_bounds.1 {
+            continue;
+        }
+
+        let shape_enabled = match skip.shape {
+            SkipShape::Straight => true,
+            SkipShape::Diagonal => config.enable_diagonal,
+            SkipShape::LShaped => config.enable_l_shaped,
+        };
+        if !shape_enabled {
+            continue;
+        }
+
+        if max_count != 0 && count >= max_count {
+            break;
+        }
+
+        let conflicting = skips
+            .iter()
+            .zip(selection.iter())
+            .filter(|(_, selected)| **selected != SkipStatus::Invalid)
+            .any(|(other, _)| {
+                skip.min_end_distance_squared(other) < config.min_spacing_sqr
+                    || skip.intersects(other)
+            });
+
+        if conflicting || !gen.rnd.with_probability(config.prob) {
+            continue;
+        }
+
+        selection[skip_index] = status.clone();
+        count += 1;
+    }
+
+    count
+}
+
 pub fn generate_all_skips(
     gen: &mut Generator,
-    length_bounds: (usize, usize),
-    min_spacing_sqr: usize,
+    skip_config: &SkipConfig,
+    freeze_skip_config: &SkipConfig,
+    max_skip_density: f32,
 ) {
     // get corner candidates
     let corner_candidates = find_corners(gen).expect("corner detection failed");
 
-    // get possible skips
+    // get possible skips, actual length bounds are checked separately for each skip type
+    let length_bounds = (
+        usize::min(
+            skip_config.length_bounds.0,
+            freeze_skip_config.length_bounds.0,
+        ),
+        usize::max(
+            skip_config.length_bounds.1,
+            freeze_skip_config.length_bounds.1,
+        ),
+    );
+    let enable_diagonal = skip_config.enable_diagonal || freeze_skip_config.enable_diagonal;
+    let enable_l_shaped = skip_config.enable_l_shaped || freeze_skip_config.enable_l_shaped;
     let mut skips: Vec<Skip> = Vec::new();
     for (start_pos, shift) in corner_candidates {
         if let Some(skip) = check_corner_skip(gen, &start_pos, &shift, length_bounds) {
             skips.push(skip);
         }
+        if enable_diagonal {
+            skips.extend(check_diagonal_skips(gen, &start_pos, &shift, length_bounds));
+        }
+        if enable_l_shaped {
+            skips.extend(check_l_skips(gen, &start_pos, &shift, length_bounds));
+        }
     }
-
-    // pick final selection of skips
+    // skips must not start, end or cut through protected areas
+    skips.retain(|skip| {
+        skip.path
+            .iter()
+            .all(|(pos, _)| !gen.map.zones[pos.as_index()].intersects(Zone::PROTECTED))
+    });
     skips.sort_unstable_by(|s1, s2| usize::cmp(&s1.length, &s2.length)); // sort by length
-    let mut valid_skips = vec![SkipStatus::Valid; skips.len()];
-    for skip_index in 0..skips.len() {
-        // skip if already invalidated
-        if valid_skips[skip_index] == SkipStatus::Invalid {
-            continue;
-        }
 
-        let skip = &skips[skip_index];
-
-        // skip if no neighboring blocks TODO: where to do dis?
-        if count_skip_neighbours(gen, skip, 2).unwrap_or(0) <= 0 {
-            // but if at least 1 direct, then to a freeze skip
-            if count_skip_neighbours(gen, skip, 1).unwrap_or(0) >= 1 {
-                valid_skips[skip_index] = SkipStatus::ValidFreezeSkipOnly;
+    // determine skip type based on neighboring blocks
+    let candidates: Vec<SkipStatus> = skips
+        .iter()
+        .map(|skip| {
+            if count_skip_neighbours(gen, skip, 2).unwrap_or(0) > 0 {
+                SkipStatus::Valid
+            } else if count_skip_neighbours(gen, skip, 1).unwrap_or(0) > 0 {
+                SkipStatus::ValidFreezeSkipOnly
             } else {
-                valid_skips[skip_index] = SkipStatus::Invalid;
-                continue;
+                SkipStatus::Invalid
             }
-        }
-
-        // skip is valid -> invalidate all following conflicting skips
-        // TODO: right now skips can still cross each other
-        // TODO: i feel like i need a config seperation between skips and freeze skips
-        //       would be nice to not have freeze invalidate actual skips, and have different
-        //       length
-        for other_index in (skip_index + 1)..skips.len() {
-            let skip_other = &skips[other_index];
-
-            // check if skips are too close
-            if skip.start_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
-                || skip.start_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
-                || skip.end_pos.distance_squared(&skip_other.start_pos) < min_spacing_sqr
-                || skip.end_pos.distance_squared(&skip_other.end_pos) < min_spacing_sqr
-            {
-                valid_skips[other_index] = SkipStatus::Invalid;
+        })
+        .collect();
+
+    // limit number of skips and freeze skips to the max density
+    let max_count = |config: &SkipConfig| match max_skip_density > 0.0 {
+        true => {
+            let max_density_count =
+                (max_skip_density * gen.walker.steps as f32 / 1000.0).ceil() as usize;
+            match config.max_count {
+                0 => max_density_count,
+                max_count => usize::min(max_count, max_density_count),
             }
         }
-    }
+        false => config.max_count,
+    };
+    let max_skips = max_count(skip_config);
+    let max_freeze_skips = max_count(freeze_skip_config);
+
+    // pick final selection of skips, freeze skips must not conflict with actual skips
+    let mut valid_skips = vec![SkipStatus::Invalid; skips.len()];
+    select_skips(
+        gen,
+        &skips,
+        &candidates,
+        &mut valid_skips,
+        SkipStatus::Valid,
+        skip_config,
+        max_skips,
+    );
+    select_skips(
+        gen,
+        &skips,
+        &candidates,
+        &mut valid_skips,
+        SkipStatus::ValidFreezeSkipOnly,
+        freeze_skip_config,
+        max_freeze_skips,
+    );
 
-    // generate all remaining valid skips
+    // generate all selected skips
     for skip_index in 0..skips.len() {
         match valid_skips[skip_index] {
             SkipStatus::Valid => generate_skip(gen, &skips[skip_index], &BlockType::Empty),
@@ -446,8 +826,9 @@ pub fn generate_all_skips(
     let debug_skips = &mut gen.debug_layers.get_mut("skips").unwrap().grid;
     for (skip, valid) in skips.iter().zip(valid_skips.iter()) {
         if *valid == SkipStatus::Valid {
-            debug_skips[skip.start_pos.as_index()] = true;
-            debug_skips[skip.end_pos.as_index()] = true;
+            for (pos, _) in skip.path.iter() {
+                debug_skips[pos.as_index()] = true;
+            }
         }
     }
 
@@ -463,8 +844,9 @@ pub fn generate_all_skips(
     let debug_freeze_skips = &mut gen.debug_layers.get_mut("freeze_skips").unwrap().grid;
     for (skip, valid) in skips.iter().zip(valid_skips.iter()) {
         if *valid == SkipStatus::ValidFreezeSkipOnly {
-            debug_freeze_skips[skip.start_pos.as_index()] = true;
-            debug_freeze_skips[skip.end_pos.as_index()] = true;
+            for (pos, _) in skip.path.iter() {
+                debug_freeze_skips[pos.as_index()] = true;
+            }
         }
     }
 }
@@ -506,7 +888,7 @@ pub fn remove_freeze_blobs(gen: &mut Generator, min_freeze_size: usize) {
             // TODO: In theory this should be a nice speedup, but in pracise i should replace this with a
             // much better two sweep approach. Idea: Do a post processing step which detects
             // 'wall'-freezes. this information can then be used in various other steps.
-            if *block_type == BlockType::Hookable {
+            if block_type.is_wall() {
                 invalid
                     .slice_mut(s![x - 1..=x + 1, y - 1..=y + 1])
                     .fill(Some(true));
@@ -527,6 +909,13 @@ pub fn remove_freeze_blobs(gen: &mut Generator, min_freeze_size: usize) {
                 let pos = blob_visit_next.pop().unwrap();
                 invalid[pos.as_index()] = Some(false); // for now we assume that current block is valid
 
+                // protected freeze blocks are never removed -> treat blob as connected
+                if gen.map.zones[pos.as_index()].intersects(Zone::PROTECTED) {
+                    blob_unconnected = false;
+                    blob_visit_next.push(pos);
+                    break;
+                }
+
                 // check neighborhood
                 let window = get_window(&gen.map.grid, pos.x, pos.y, window_size);
                 for ((win_x, win_y), other_block_type) in window.indexed_iter() {
@@ -595,3 +984,105 @@ pub fn remove_freeze_blobs(gen: &mut Generator, min_freeze_size: usize) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{GenerationConfig, MapConfig};
+    use crate::random::Seed;
+    use std::collections::HashMap;
+
+    #[test]
+    fn fill_open_areas_keeps_no_obstacle_zones() {
+        let mut gen = Generator::new(
+            &GenerationConfig::default(),
+            &MapConfig::default(),
+            Seed::from_u64(0),
+        );
+
+        // large open area in a solid map, with a no obstacle zone at its center
+        gen.map.set_area(
+            &Position::new(100, 100),
+            &Position::new(200, 200),
+            &BlockType::Empty,
+            &Overwrite::Force,
+        );
+        let zone_top_left = Position::new(140, 140);
+        let zone_bot_right = Position::new(160, 160);
+        gen.map
+            .add_zone(&zone_top_left, &zone_bot_right, Zone::NO_OBSTACLE);
+
+        fill_open_areas(&mut gen, &5.0);
+
+        assert_eq!(gen.map.grid[[120, 150]], BlockType::Hookable);
+        assert!(gen
+            .map
+            .check_area_all(&zone_top_left, &zone_bot_right, &BlockType::Empty)
+            .unwrap());
+    }
+
+    #[test]
+    fn tele_tiles_have_nonzero_numbers() {
+        let gen_config = GenerationConfig {
+            enable_tele_sections: true,
+            tele_section_prob: 1.0,
+            enable_checkpoint_traps: true,
+            checkpoint_trap_prob: 1.0,
+            ..GenerationConfig::default()
+        };
+        let map_config = MapConfig::default();
+        let gen_configs = HashMap::new();
+
+        let (map, _) = Generator::generate_map(
+            200_000,
+            &Seed::from_u64(0),
+            &gen_config,
+            &map_config,
+            &gen_configs,
+        )
+        .unwrap();
+
+        let tele = map.tele.expect("map has no tele layer");
+        let tiles: Vec<&TeleTile> = tele.iter().flatten().collect();
+        assert!(tiles
+            .iter()
+            .any(|tile| tile.tele_type == TeleType::CheckpointIn));
+        assert!(tiles.iter().all(|tile| tile.number != 0));
+    }
+
+    #[test]
+    fn noise_obstacles_match_density() {
+        let obstacle_count = |density: f32| {
+            let mut gen = Generator::new(
+                &GenerationConfig::default(),
+                &MapConfig::default(),
+                Seed::from_u64(0),
+            );
+            gen.map.set_area(
+                &Position::new(100, 100),
+                &Position::new(200, 200),
+                &BlockType::Empty,
+                &Overwrite::Force,
+            );
+
+            let obstacles = generate_noise_obstacles(&mut gen, density, 6.0, 3.0);
+            obstacles.iter().filter(|obstacle| **obstacle).count()
+        };
+
+        let eligible_count = obstacle_count(1.0);
+        assert!(eligible_count > 0);
+        assert_eq!(obstacle_count(0.0), 0);
+
+        for density in [0.1, 0.3, 0.5] {
+            let expected = eligible_count as f32 * density;
+            let count = obstacle_count(density) as f32;
+            assert!(
+                (count - expected).abs() <= 0.1 * expected,
+                "density {}: {} obstacles, expected {}",
+                density,
+                count,
+                expected
+            );
+        }
+    }
+}
@@ -0,0 +1,346 @@
+use crate::{
+    map::{BlockType, Map, Overwrite, Zone},
+    position::{Position, ShiftDirection},
+};
+use log::warn;
+use ndarray::Array2;
+use rust_embed::RustEmbed;
+use serde::{Deserialize, Serialize};
+
+#[derive(RustEmbed)]
+#[folder = "data/prefabs/"]
+pub struct PrefabStorage;
+
+/// Serialized prefab. Blocks are defined row by row using the following chars:
+/// `#` hookable, `~` freeze, ` ` empty, `.` empty and reserved, `=` platform and `*` for keeping
+/// whatever block is already there (no obstacles are placed there).
+#[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
+pub struct PrefabConfig {
+    /// name of the prefab
+    pub name: String,
+
+    /// block rows of the prefab, all rows must have the same length
+    pub rows: Vec<String>,
+
+    /// position in the prefab that is placed at the walker position
+    pub entry: Position,
+
+    /// walker direction that is required to enter the prefab
+    pub entry_dir: ShiftDirection,
+
+    /// position in the prefab where the walker continues after the prefab was placed
+    pub exit: Position,
+
+    /// walker direction when leaving the prefab
+    pub exit_dir: ShiftDirection,
+}
+
+#[derive(Debug, Clone)]
+pub struct Prefab {
+    pub name: String,
+
+    /// blocks of the prefab, None blocks keep the current map block
+    pub blocks: Array2<Option<BlockType>>,
+
+    /// zones added to the map for each block of the prefab
+    pub zones: Array2<Zone>,
+
+    pub entry: Position,
+    pub entry_dir: ShiftDirection,
+    pub exit: Position,
+    pub exit_dir: ShiftDirection,
+}
+
+impl Prefab {
+    pub fn from_config(config: &PrefabConfig) -> Result<Prefab, &'static str> {
+        let height = config.rows.len();
+        let width = config
+            .rows
+            .first()
+            .ok_or("prefab has no rows")?
+            .chars()
+            .count();
+
+        let mut blocks = Array2::from_elem((width, height), None);
+        let mut zones = Array2::from_elem((width, height), Zone::PREFAB);
+        for (y, row) in config.rows.iter().enumerate() {
+            if row.chars().count() != width {
+                return Err("prefab rows have different lengths");
+            }
+
+            for (x, char) in row.chars().enumerate() {
+                blocks[[x, y]] = match char {
+                    '#' => Some(BlockType::Hookable),
+                    '~' => Some(BlockType::Freeze),
+                    ' ' => Some(BlockType::Empty),
+                    '.' => {
+                        zones[[x, y]] |= Zone::RESERVED;
+                        Some(BlockType::Empty)
+                    }
+                    '=' => Some(BlockType::Platform),
+                    '*' => {
+                        zones[[x, y]] = Zone::NO_OBSTACLE;
+                        None
+                    }
+                    _ => return Err("prefab contains unknown block char"),
+                };
+            }
+        }
+
+        if config.entry.x >= width
+            || config.entry.y >= height
+            || config.exit.x >= width
+            || config.exit.y >= height
+        {
+            return Err("prefab entry/exit out of bounds");
+        }
+
+        Ok(Prefab {
+            name: config.name.clone(),
+            blocks,
+            zones,
+            entry: config.entry.clone(),
+            entry_dir: config.entry_dir,
+            exit: config.exit.clone(),
+            exit_dir: config.exit_dir,
+        })
+    }
+
+    /// returns a copy of the prefab rotated by 90 degrees clockwise
+    pub fn rotated_cw(&self) -> Prefab {
+        let (width, height) = self.blocks.dim();
+        let rotate = |pos: &Position| Position::new(height - 1 - pos.y, pos.x);
+
+        let mut blocks = Array2::from_elem((height, width), None);
+        let mut zones = Array2::from_elem((height, width), Zone::NONE);
+        for ((x, y), block) in self.blocks.indexed_iter() {
+            let rotated = rotate(&Position::new(x, y));
+            blocks[rotated.as_index()] = block.clone();
+            zones[rotated.as_index()] = self.zones[[x, y]];
+        }
+
+        Prefab {
+            name: self.name.clone(),
+            blocks,
+            zones,
+            entry: rotate(&self.entry),
+            entry_dir: self.entry_dir.rotated_cw(),
+            exit: rotate(&self.exit),
+            exit_dir: self.exit_dir.rotated_cw(),
+        }
+    }
+
+    /// parses a serialized PrefabConfig
+    pub fn from_json(data: &str) -> Result<Prefab, String> {
+        let config = serde_json::from_str::<PrefabConfig>(data).map_err(|e| e.to_string())?;
+        Prefab::from_config(&config).map_err(|e| e.to_string())
+    }
+
+    /// loads all embedded prefabs in all four rotations
+    pub fn get_all_prefabs() -> Vec<Prefab> {
+        let mut prefabs = Vec::new();
+
+        for file_name in PrefabStorage::iter() {
+            let file = PrefabStorage::get(&file_name).unwrap();
+            let data = std::str::from_utf8(&file.data).unwrap();
+
+            match Prefab::from_json(data) {
+                Ok(mut prefab) => {
+                    for _ in 0..4 {
+                        let rotated = prefab.rotated_cw();
+                        prefabs.push(prefab);
+                        prefab = rotated;
+                    }
+                }
+                Err(e) => {
+                    warn!("couldn't parse prefab {}: {}", file_name, e);
+                }
+            }
+        }
+
+        prefabs
+    }
+
+    /// Places the prefab so that its entry is located at the given position. Placement is
+    /// refused if the prefab would overlap protected blocks or if its exit is closer than margin
+    /// to the map border. Only solid blocks are replaced, so a prefab never closes off corridors
+    /// that have already been carved. Returns the map position of the exit.
+    pub fn stamp(
+        &self,
+        map: &mut Map,
+        entry_pos: &Position,
+        margin: usize,
+    ) -> Result<Position, &'static str> {
+        let (width, height) = self.blocks.dim();
+        let top_left = entry_pos.shifted_by(-(self.entry.x as i32), -(self.entry.y as i32))?;
+        let bot_right = top_left.shifted_by(width as i32 - 1, height as i32 - 1)?;
+        let exit_pos = top_left.shifted_by(self.exit.x as i32, self.exit.y as i32)?;
+
+        let margin = margin as i32;
+        if !map.pos_in_bounds(&bot_right)
+            || !map.pos_in_bounds(&exit_pos.shifted_by(margin, margin)?)
+            || exit_pos.shifted_by(-margin, -margin).is_err()
+        {
+            return Err("prefab out of bounds");
+        }
+
+        if map.check_zone_exists(&top_left, &bot_right, Zone::PROTECTED)? {
+            return Err("prefab overlaps protected area");
+        }
+
+        for ((x, y), block) in self.blocks.indexed_iter() {
+            let pos = Position::new(top_left.x + x, top_left.y + y);
+            if let Some(block) = block {
+                map.set_area(&pos, &pos, block, &Overwrite::ReplaceSolidOnly);
+            }
+
+            // zones are also added for kept blocks, so no obstacles are placed there
+            map.add_zone(&pos, &pos, self.zones[[x, y]]);
+        }
+
+        Ok(exit_pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MAP_SIZE: usize = 80;
+
+    /// solid map with a horizontal and a vertical corridor crossing at the center
+    fn carved_map() -> Map {
+        let mut map = Map::new(MAP_SIZE, MAP_SIZE, BlockType::Hookable);
+        let center = MAP_SIZE / 2;
+        map.set_area(
+            &Position::new(10, center - 1),
+            &Position::new(MAP_SIZE - 10, center + 1),
+            &BlockType::Empty,
+            &Overwrite::Force,
+        );
+        map.set_area(
+            &Position::new(center - 1, 10),
+            &Position::new(center + 1, MAP_SIZE - 10),
+            &BlockType::Empty,
+            &Overwrite::Force,
+        );
+
+        map
+    }
+
+    fn test_config(rows: &[&str], entry: Position, exit: Position) -> PrefabConfig {
+        PrefabConfig {
+            name: "test".to_string(),
+            rows: rows.iter().map(|row| row.to_string()).collect(),
+            entry,
+            entry_dir: ShiftDirection::Right,
+            exit,
+            exit_dir: ShiftDirection::Up,
+        }
+    }
+
+    #[test]
+    fn embedded_prefabs_are_valid() {
+        for file_name in PrefabStorage::iter() {
+            let file = PrefabStorage::get(&file_name).unwrap();
+            let data = std::str::from_utf8(&file.data).unwrap();
+            if let Err(e) = Prefab::from_json(data) {
+                panic!("couldn't parse prefab {}: {}", file_name, e);
+            }
+        }
+    }
+
+    #[test]
+    fn from_config_rejects_invalid_prefabs() {
+        let ragged = test_config(
+            &["###", "##", "###"],
+            Position::new(0, 0),
+            Position::new(1, 1),
+        );
+        assert!(Prefab::from_config(&ragged).is_err());
+
+        let exit_out_of_bounds = test_config(
+            &["###", "###", "###"],
+            Position::new(0, 0),
+            Position::new(3, 1),
+        );
+        assert!(Prefab::from_config(&exit_out_of_bounds).is_err());
+    }
+
+    #[test]
+    fn rotated_cw_rotates_entry_and_exit() {
+        // entry at the left of the bottom row, exit at the right of the top row
+        let config = test_config(&["## ", " ~#"], Position::new(0, 1), Position::new(2, 0));
+        let prefab = Prefab::from_config(&config).unwrap();
+
+        let rotated = prefab.rotated_cw();
+        assert_eq!(rotated.blocks.dim(), (2, 3));
+        assert_eq!(rotated.entry, Position::new(0, 0));
+        assert_eq!(rotated.entry_dir, ShiftDirection::Down);
+        assert_eq!(rotated.exit, Position::new(1, 2));
+        assert_eq!(rotated.exit_dir, ShiftDirection::Right);
+
+        // the blocks at entry and exit are rotated along with them
+        for prefab in Prefab::get_all_prefabs() {
+            let rotated = prefab.rotated_cw();
+            assert_eq!(
+                rotated.blocks[rotated.entry.as_index()],
+                prefab.blocks[prefab.entry.as_index()]
+            );
+            assert_eq!(
+                rotated.blocks[rotated.exit.as_index()],
+                prefab.blocks[prefab.exit.as_index()]
+            );
+
+            let full_rotation = rotated.rotated_cw().rotated_cw().rotated_cw();
+            assert_eq!(full_rotation.blocks, prefab.blocks);
+            assert_eq!(full_rotation.entry, prefab.entry);
+            assert_eq!(full_rotation.entry_dir, prefab.entry_dir);
+            assert_eq!(full_rotation.exit, prefab.exit);
+            assert_eq!(full_rotation.exit_dir, prefab.exit_dir);
+        }
+    }
+
+    #[test]
+    fn stamp_refuses_protected_areas() {
+        let center = Position::new(MAP_SIZE / 2, MAP_SIZE / 2);
+
+        for prefab in Prefab::get_all_prefabs() {
+            let mut map = carved_map();
+            map.add_zone(&center, &center, Zone::PROTECTED);
+            let grid = map.grid.clone();
+
+            assert!(prefab.stamp(&mut map, &center, 0).is_err());
+            assert_eq!(map.grid, grid, "prefab {} changed the map", prefab.name);
+        }
+    }
+
+    #[test]
+    fn stamp_keeps_carved_blocks_empty() {
+        let prefabs = Prefab::get_all_prefabs();
+        assert!(!prefabs.is_empty());
+
+        for prefab in prefabs.iter() {
+            for (dx, dy) in [(0, 0), (-4, 0), (0, 3), (5, -5)] {
+                let mut map = carved_map();
+                let carved = map.grid.map(|block| *block == BlockType::Empty);
+                let entry_pos = Position::new(
+                    (MAP_SIZE / 2).saturating_add_signed(dx),
+                    (MAP_SIZE / 2).saturating_add_signed(dy),
+                );
+
+                prefab.stamp(&mut map, &entry_pos, 0).unwrap();
+
+                for ((x, y), was_carved) in carved.indexed_iter() {
+                    assert!(
+                        !*was_carved || map.grid[[x, y]] == BlockType::Empty,
+                        "prefab {} closed off carved block ({}, {})",
+                        prefab.name,
+                        x,
+                        y
+                    );
+                }
+            }
+        }
+    }
+}
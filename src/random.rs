@@ -39,6 +39,7 @@ impl<T> RandomDistConfig<T> {
     }
 }
 
+#[derive(Clone)]
 pub struct RandomDist<T> {
     rnd_cfg: RandomDistConfig<T>,
     rnd_dist: WeightedAliasIndex<f32>,
@@ -60,6 +61,7 @@ impl<T: Clone> RandomDist<T> {
     }
 }
 
+#[derive(Clone)]
 pub struct Random {
     pub seed: Seed,
     gen: SmallRng,
@@ -67,6 +69,7 @@ pub struct Random {
     inner_kernel_size_dist: RandomDist<usize>,
     outer_kernel_margin_dist: RandomDist<usize>,
     circ_dist: RandomDist<f32>,
+    prefab_dist: RandomDist<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -112,10 +115,25 @@ impl Random {
             outer_kernel_margin_dist: RandomDist::new(config.outer_margin_probs.clone()),
             inner_kernel_size_dist: RandomDist::new(config.inner_size_probs.clone()),
             circ_dist: RandomDist::new(config.circ_probs.clone()),
+            prefab_dist: RandomDist::new(config.prefab_probs.clone()),
             // TODO: clones here fine?
         }
     }
 
+    /// replaces the PRNG state, keeps the seed and all distributions
+    pub fn reseed(&mut self, seed_u64: u64) {
+        self.gen = SmallRng::seed_from_u64(seed_u64);
+    }
+
+    /// replaces all distributions with the ones of the given config, keeps the PRNG state
+    pub fn update_dists(&mut self, config: &GenerationConfig) {
+        self.shift_dist = RandomDist::new(config.shift_weights.clone());
+        self.outer_kernel_margin_dist = RandomDist::new(config.outer_margin_probs.clone());
+        self.inner_kernel_size_dist = RandomDist::new(config.inner_size_probs.clone());
+        self.circ_dist = RandomDist::new(config.circ_probs.clone());
+        self.prefab_dist = RandomDist::new(config.prefab_probs.clone());
+    }
+
     pub fn sample_inner_kernel_size(&mut self) -> usize {
         let dist = &self.inner_kernel_size_dist;
         let index = dist.rnd_dist.sample(&mut self.gen);
@@ -152,6 +170,18 @@ impl Random {
             .clone()
     }
 
+    pub fn sample_prefab_name(&mut self) -> String {
+        let dist = &self.prefab_dist;
+        let index = dist.rnd_dist.sample(&mut self.gen);
+        dist.rnd_cfg
+            .values
+            .as_ref()
+            .unwrap()
+            .get(index)
+            .unwrap()
+            .clone()
+    }
+
     pub fn sample_shift(&mut self, ordered_shifts: &[ShiftDirection; 4]) -> ShiftDirection {
         let dist = &self.shift_dist;
         let index = dist.rnd_dist.sample(&mut self.gen);
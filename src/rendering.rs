@@ -1,4 +1,9 @@
-use crate::{map::BlockType, map::KernelType, position::Position, walker::CuteWalker};
+use crate::{
+    map::{BlockType, KernelType, Map, Zone},
+    physics_layers::{PhysicsLayer, SpeedupTile, TeleTile, TeleType},
+    position::Position,
+    walker::CuteWalker,
+};
 use macroquad::color::colors;
 use macroquad::color::Color;
 use macroquad::shapes::*;
@@ -7,9 +12,9 @@ use ndarray::Array2;
 fn blocktype_to_color(value: &BlockType) -> Color {
     match value {
         BlockType::Hookable => colors::BROWN,
+        BlockType::Unhookable => Color::new(0.55, 0.55, 0.6, 1.0),
         BlockType::Freeze => Color::new(0.0, 0.0, 0.0, 0.8),
         BlockType::Empty => Color::new(0.0, 0.0, 0.0, 0.0),
-        BlockType::EmptyReserved => Color::new(0.3, 0.0, 0.0, 0.1),
         BlockType::Finish => Color::new(1.0, 0.1, 0.1, 0.8),
         BlockType::Start => Color::new(0.1, 1.0, 0.1, 0.8),
         BlockType::Platform => Color::new(0.5, 0.5, 0.0, 0.8),
@@ -45,6 +50,7 @@ pub fn draw_bool_grid(grid: &Array2<bool>, color: &Color, outline: &bool) {
 /// drawn individually as in the unoptimized variant.
 pub fn draw_chunked_grid(
     grid: &Array2<BlockType>,
+    zones: &Array2<Zone>,
     chunks_edited: &Array2<bool>,
     chunk_size: usize,
 ) {
@@ -59,6 +65,17 @@ pub fn draw_chunked_grid(
                 for y in y_start..y_end {
                     let value = &grid[[x, y]];
                     draw_rectangle(x as f32, y as f32, 1.0, 1.0, blocktype_to_color(value));
+
+                    // tint protected blocks
+                    if zones[[x, y]].intersects(Zone::PROTECTED) {
+                        draw_rectangle(
+                            x as f32,
+                            y as f32,
+                            1.0,
+                            1.0,
+                            Color::new(0.3, 0.0, 0.0, 0.1),
+                        );
+                    }
                 }
             }
         } else {
@@ -75,6 +92,42 @@ pub fn draw_chunked_grid(
     }
 }
 
+fn teletype_to_color(value: &TeleType) -> Color {
+    match value {
+        TeleType::In | TeleType::EvilIn => Color::new(0.6, 0.1, 0.9, 0.6),
+        TeleType::Out => Color::new(0.9, 0.4, 1.0, 0.8),
+        TeleType::Checkpoint => Color::new(0.1, 0.6, 0.9, 0.4),
+        TeleType::CheckpointIn | TeleType::EvilCheckpointIn => Color::new(0.9, 0.1, 0.6, 0.6),
+        TeleType::CheckpointOut => Color::new(0.1, 0.8, 1.0, 0.8),
+    }
+}
+
+fn speedup_to_color(_value: &SpeedupTile) -> Color {
+    Color::new(1.0, 0.8, 0.1, 0.4)
+}
+
+/// Draws all tiles of an optional physics layer on top of the map.
+pub fn draw_physics_layer<T, F>(layer: &Option<PhysicsLayer<T>>, to_color: F)
+where
+    F: Fn(&T) -> Color,
+{
+    if let Some(layer) = layer {
+        for ((x, y), tile) in layer.indexed_iter() {
+            if let Some(tile) = tile {
+                draw_rectangle(x as f32, y as f32, 1.0, 1.0, to_color(tile));
+            }
+        }
+    }
+}
+
+/// Draws the tele and speedup layers of a map.
+pub fn draw_physics_layers(map: &Map) {
+    draw_physics_layer(&map.tele, |tile: &TeleTile| {
+        teletype_to_color(&tile.tele_type)
+    });
+    draw_physics_layer(&map.speedup, speedup_to_color);
+}
+
 pub fn draw_walker(walker: &CuteWalker) {
     draw_rectangle_lines(
         walker.pos.x as f32,
@@ -97,10 +150,10 @@ pub fn draw_walker_kernel(walker: &CuteWalker, kernel_type: KernelType) {
         KernelType::Inner => &walker.inner_kernel,
         KernelType::Outer => &walker.outer_kernel,
     };
-    let offset: usize = kernel.size / 2; // offset of kernel wrt. position (top/left)
+    let (offset, _) = kernel.get_extends(&walker.anchor); // offset of kernel wrt. position (top/left)
 
-    let root_x = walker.pos.x.checked_sub(offset);
-    let root_y = walker.pos.y.checked_sub(offset);
+    let root_x = walker.pos.x.checked_sub(offset.0);
+    let root_y = walker.pos.y.checked_sub(offset.1);
 
     if root_x.is_none() || root_y.is_none() {
         return; // dont draw as the following draw operation would fail
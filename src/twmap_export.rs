@@ -1,13 +1,32 @@
-use crate::map::{BlockTypeTW, Map};
+use crate::decoration::Decoration;
+use crate::map::{BlockType, BlockTypeTW, Map};
+use crate::physics_layers::{PhysicsLayer, SpeedupTile, TuneTile};
 use crate::position::Position;
-use ndarray::{Array2};
+use fixed::types::I17F15;
+use ndarray::Array2;
 use rust_embed::RustEmbed;
 use std::path::PathBuf;
 use twmap::{
     automapper::{self, Automapper},
-    GameLayer, GameTile, Layer, Tile, TileFlags, TilemapLayer, TilesLayer, TwMap,
+    AutomapperConfig, CompressedData, ExternalImage, FrontLayer, GameLayer, GameTile, Group, Image,
+    Layer, PhysicsLayer as TwPhysicsLayer, Quad, QuadsLayer, Speedup, SpeedupLayer, Switch,
+    SwitchLayer, Tele, TeleLayer, Tile, TileFlags, TilemapLayer, TilesLayer, Tune, TuneLayer,
+    TwMap,
 };
 
+/// mapres image and automapper rules used for the decoration background
+const BACKGROUND_IMAGE: &str = "jungle_midground";
+const BACKGROUND_IMAGE_SIZE: u32 = 1024;
+const BACKGROUND_RULE_INDEX: u16 = 1; // "Small"
+
+/// mapres image and automapper rules used for unhookable walls
+const UNHOOKABLE_IMAGE: &str = "generic_unhookable";
+const UNHOOKABLE_IMAGE_SIZE: u32 = 1024;
+const UNHOOKABLE_RULE_INDEX: u16 = 0; // "Random Silver"
+
+/// size of the sky quad in blocks, large enough to cover the screen at any zoom level
+const SKY_SIZE: (f32, f32) = (100.0, 60.0);
+
 #[derive(RustEmbed)]
 #[folder = "automapper/"]
 pub struct AutoMapperConfigs;
@@ -36,6 +55,48 @@ impl TwExport {
         automapper_config.clone()
     }
 
+    /// fills a tiles layer with all blocks of the given type and runs its automapper config
+    fn fill_tiles_layer(
+        layer: &mut TilesLayer,
+        image_name: String,
+        map: &Map,
+        layer_type: &BlockTypeTW,
+    ) {
+        let automapper_config = TwExport::get_automapper_config(image_name, layer);
+
+        let tiles = layer.tiles_mut().unwrap_mut();
+        *tiles = Array2::<Tile>::default((map.height, map.width));
+
+        for ((x, y), block_type) in map.grid.indexed_iter() {
+            let block_type = block_type.to_tw_block_type();
+            let mut set_block: bool = *layer_type == block_type;
+
+            // custom rule for freeze
+            if layer_type == &BlockTypeTW::Freeze
+                && matches!(block_type, BlockTypeTW::Hookable | BlockTypeTW::Unhookable)
+            {
+                let shifts = &[(-1, 0), (0, -1), (1, 0), (0, 1)];
+                for shift in shifts {
+                    let neighbor_type = Position::new(x, y)
+                        .shifted_by(shift.0, shift.1)
+                        .ok()
+                        .and_then(|pos| map.grid.get(pos.as_index()));
+
+                    if neighbor_type.is_some_and(|t| t.is_freeze()) {
+                        set_block = true;
+                        break;
+                    }
+                }
+            }
+
+            if set_block {
+                tiles[[y, x]] = Tile::new(1, TileFlags::empty())
+            }
+        }
+
+        automapper_config.run(3777777777, tiles) // thanks Tater for the epic **random** seed
+    }
+
     pub fn process_layer(
         tw_map: &mut TwMap,
         map: &Map,
@@ -50,37 +111,7 @@ impl TwExport {
             assert_eq!(layer.name, layer_name);
 
             let image_name = tw_map.images[layer.image.unwrap() as usize].name();
-            let automapper_config = TwExport::get_automapper_config(image_name.clone(), layer);
-
-            let tiles = layer.tiles_mut().unwrap_mut();
-            *tiles = Array2::<Tile>::default((map.height, map.width));
-
-            for ((x, y), block_type) in map.grid.indexed_iter() {
-                let block_type = block_type.to_tw_block_type();
-                let mut set_block: bool = *layer_type == block_type;
-
-                // custom rule for freeze
-                if layer_type == &BlockTypeTW::Freeze && block_type == BlockTypeTW::Hookable {
-                    let shifts = &[(-1, 0), (0, -1), (1, 0), (0, 1)];
-                    for shift in shifts {
-                        let neighbor_type = Position::new(x, y)
-                            .shifted_by(shift.0, shift.1)
-                            .ok()
-                            .and_then(|pos| map.grid.get(pos.as_index()));
-
-                        if neighbor_type.is_some_and(|t| t.is_freeze()) {
-                            set_block = true;
-                            break;
-                        }
-                    }
-                }
-
-                if set_block {
-                    tiles[[y, x]] = Tile::new(1, TileFlags::empty())
-                }
-            }
-
-            automapper_config.run(3777777777, tiles) // thanks Tater for the epic **random** seed
+            TwExport::fill_tiles_layer(layer, image_name.clone(), map, layer_type);
         } else {
             panic!(
                 "coulnt get layer at index {:} ({:})",
@@ -89,6 +120,211 @@ impl TwExport {
         };
     }
 
+    /// Writes all unhookable blocks into the "Unhookable" layer of the "Tiles" group. The layer
+    /// is added if the template doesn't contain it yet.
+    pub fn process_unhookable_layer(tw_map: &mut TwMap, map: &Map) {
+        let image =
+            TwExport::get_or_add_external_image(tw_map, UNHOOKABLE_IMAGE, UNHOOKABLE_IMAGE_SIZE);
+
+        let tile_group = tw_map
+            .groups
+            .iter_mut()
+            .find(|group| group.name == "Tiles")
+            .expect("template has no Tiles group");
+
+        let is_unhookable_layer =
+            |layer: &Layer| matches!(layer, Layer::Tiles(layer) if layer.name == "Unhookable");
+        if !tile_group.layers.iter().any(is_unhookable_layer) {
+            tile_group
+                .layers
+                .push(Layer::Tiles(TwExport::new_tiles_layer(
+                    "Unhookable",
+                    image,
+                    UNHOOKABLE_RULE_INDEX,
+                )));
+        }
+
+        if let Some(Layer::Tiles(layer)) = tile_group
+            .layers
+            .iter_mut()
+            .find(|layer| is_unhookable_layer(layer))
+        {
+            let image_name = tw_map.images[layer.image.unwrap() as usize].name();
+            TwExport::fill_tiles_layer(layer, image_name.clone(), map, &BlockTypeTW::Unhookable);
+        }
+    }
+
+    /// Writes an optional physics layer of the map into the tw map. The physics layer is added
+    /// to the physics group if the tw map doesn't contain it yet.
+    pub fn process_physics_layer<T, L, F>(
+        tw_map: &mut TwMap,
+        layer: &PhysicsLayer<T>,
+        new_layer: fn() -> Layer,
+        to_tw_tile: F,
+    ) where
+        L: TwPhysicsLayer,
+        L::TileType: Default + Clone,
+        F: Fn(&T) -> L::TileType,
+    {
+        if tw_map.find_physics_layer::<L>().is_none() {
+            tw_map.physics_group_mut().layers.push(new_layer());
+        }
+
+        let (width, height) = layer.dim();
+        let tiles = tw_map
+            .find_physics_layer_mut::<L>()
+            .unwrap()
+            .tiles_mut()
+            .unwrap_mut();
+
+        *tiles = Array2::from_shape_fn((height, width), |(y, x)| {
+            layer[[x, y]].as_ref().map(&to_tw_tile).unwrap_or_default()
+        });
+    }
+
+    /// returns the index of the external image with the given name, adds it if it's missing
+    fn get_or_add_external_image(tw_map: &mut TwMap, name: &str, size: u32) -> u16 {
+        if let Some(index) = tw_map.images.iter().position(|image| image.name() == name) {
+            return index as u16;
+        }
+
+        tw_map.images.push(Image::External(ExternalImage {
+            name: name.to_string(),
+            size: (size, size).into(),
+        }));
+
+        (tw_map.images.len() - 1) as u16
+    }
+
+    /// creates an empty tiles layer using the given image and automapper config index
+    fn new_tiles_layer(name: &str, image: u16, rule_index: u16) -> TilesLayer {
+        TilesLayer {
+            name: name.to_string(),
+            detail: false,
+            color: (255, 255, 255, 255).into(),
+            color_env: None,
+            color_env_offset: 0,
+            image: Some(image),
+            tiles: CompressedData::Loaded(Array2::default((1, 1))),
+            automapper_config: AutomapperConfig {
+                config: Some(rule_index),
+                seed: 0,
+                automatic: false,
+            },
+        }
+    }
+
+    fn new_group(name: &str, parallax: i32, layers: Vec<Layer>) -> Group {
+        Group {
+            name: name.to_string(),
+            offset: Default::default(),
+            parallax: (parallax, parallax).into(),
+            layers,
+            clipping: false,
+            clip: Default::default(),
+            clip_size: Default::default(),
+        }
+    }
+
+    /// creates an untextured quad from its center and size in blocks. Colors are given for
+    /// the top left, top right, bottom left and bottom right corner.
+    fn new_quad(pos: (f32, f32), size: (f32, f32), colors: [(u8, u8, u8, u8); 4]) -> Quad {
+        let point = |x: f32, y: f32| (I17F15::from_num(x), I17F15::from_num(y)).into();
+        let (half_w, half_h) = (size.0 / 2.0, size.1 / 2.0);
+
+        let mut quad = Quad::new(
+            point(pos.0, pos.1),
+            (I17F15::from_num(size.0), I17F15::from_num(size.1)).into(),
+        )
+        .expect("quad exceeds map bounds");
+
+        quad.position = point(pos.0, pos.1);
+        quad.corners = [
+            point(pos.0 - half_w, pos.1 - half_h),
+            point(pos.0 + half_w, pos.1 - half_h),
+            point(pos.0 - half_w, pos.1 + half_h),
+            point(pos.0 + half_w, pos.1 + half_h),
+        ];
+        quad.colors = colors.map(Into::into);
+
+        quad
+    }
+
+    /// Writes the visual decoration into new groups. The sky and background quads are placed
+    /// behind everything else, the background tiles directly behind the "Tiles" group.
+    pub fn process_decoration(tw_map: &mut TwMap, decoration: &Decoration) {
+        // background tiles
+        let image =
+            TwExport::get_or_add_external_image(tw_map, BACKGROUND_IMAGE, BACKGROUND_IMAGE_SIZE);
+
+        let (width, height) = decoration.background.dim();
+        let mut layer = TwExport::new_tiles_layer("Background", image, BACKGROUND_RULE_INDEX);
+        *layer.tiles_mut().unwrap_mut() = Array2::default((height, width));
+
+        let automapper_config =
+            TwExport::get_automapper_config(BACKGROUND_IMAGE.to_string(), &layer);
+        let tiles = layer.tiles_mut().unwrap_mut();
+        for ((x, y), background) in decoration.background.indexed_iter() {
+            if *background {
+                tiles[[y, x]] = Tile::new(1, TileFlags::empty());
+            }
+        }
+        automapper_config.run(3777777777, tiles);
+
+        let tiles_group_index = tw_map
+            .groups
+            .iter()
+            .position(|group| group.name == "Tiles")
+            .expect("template has no Tiles group");
+        tw_map.groups.insert(
+            tiles_group_index,
+            TwExport::new_group("Background", 100, vec![Layer::Tiles(layer)]),
+        );
+
+        // background quads
+        let quads = decoration
+            .quads
+            .iter()
+            .map(|quad| TwExport::new_quad(quad.pos, quad.size, [quad.color; 4]))
+            .collect();
+        let quads_layer = QuadsLayer {
+            name: "Quads".to_string(),
+            detail: false,
+            quads,
+            image: None,
+        };
+        tw_map.groups.insert(
+            0,
+            TwExport::new_group(
+                "Background Quads",
+                decoration.quad_parallax,
+                vec![Layer::Quads(quads_layer)],
+            ),
+        );
+
+        // sky gradient, parallax 0 keeps it fixed on screen
+        let (top, bottom) = decoration.sky_colors;
+        let sky_layer = QuadsLayer {
+            name: "Sky".to_string(),
+            detail: false,
+            quads: vec![TwExport::new_quad(
+                (0.0, 0.0),
+                SKY_SIZE,
+                [
+                    (top.0, top.1, top.2, 255),
+                    (top.0, top.1, top.2, 255),
+                    (bottom.0, bottom.1, bottom.2, 255),
+                    (bottom.0, bottom.1, bottom.2, 255),
+                ],
+            )],
+            image: None,
+        };
+        tw_map.groups.insert(
+            0,
+            TwExport::new_group("Sky", 0, vec![Layer::Quads(sky_layer)]),
+        );
+    }
+
     pub fn export(map: &Map, path: &PathBuf) {
         let mut tw_map = TwMap::parse_file("automap_test.map").expect("parsing failed");
         tw_map.load().expect("loading failed");
@@ -96,6 +332,10 @@ impl TwExport {
         TwExport::process_layer(&mut tw_map, map, &0, "Freeze", &BlockTypeTW::Freeze);
         TwExport::process_layer(&mut tw_map, map, &1, "Hookable", &BlockTypeTW::Hookable);
 
+        if map.grid.iter().any(|block| *block == BlockType::Unhookable) {
+            TwExport::process_unhookable_layer(&mut tw_map, map);
+        }
+
         // get game layer
         let game_layer = tw_map
             .find_physics_layer_mut::<GameLayer>()
@@ -113,6 +353,94 @@ impl TwExport {
             game_layer[[y, x]] = GameTile::new(value.to_tw_game_id(), TileFlags::empty())
         }
 
+        // add optional physics layers
+        if let Some(front) = &map.front {
+            TwExport::process_physics_layer::<_, FrontLayer, _>(
+                &mut tw_map,
+                front,
+                || {
+                    Layer::Front(FrontLayer {
+                        tiles: CompressedData::Loaded(Array2::default((1, 1))),
+                    })
+                },
+                |block_type| GameTile::new(block_type.to_tw_game_id(), TileFlags::empty()),
+            );
+        }
+
+        if let Some(tele) = &map.tele {
+            TwExport::process_physics_layer::<_, TeleLayer, _>(
+                &mut tw_map,
+                tele,
+                || {
+                    Layer::Tele(TeleLayer {
+                        tiles: CompressedData::Loaded(Array2::default((1, 1))),
+                    })
+                },
+                |tile| Tele {
+                    number: tile.number,
+                    id: tile.tele_type.to_tw_tele_id(),
+                },
+            );
+        }
+
+        if let Some(speedup) = &map.speedup {
+            TwExport::process_physics_layer::<_, SpeedupLayer, _>(
+                &mut tw_map,
+                speedup,
+                || {
+                    Layer::Speedup(SpeedupLayer {
+                        tiles: CompressedData::Loaded(Array2::default((1, 1))),
+                    })
+                },
+                |tile| Speedup {
+                    force: tile.force,
+                    max_speed: tile.max_speed,
+                    id: SpeedupTile::TW_SPEEDUP_ID,
+                    unused_padding: 0,
+                    angle: tile.angle.into(),
+                },
+            );
+        }
+
+        if let Some(switch) = &map.switch {
+            TwExport::process_physics_layer::<_, SwitchLayer, _>(
+                &mut tw_map,
+                switch,
+                || {
+                    Layer::Switch(SwitchLayer {
+                        tiles: CompressedData::Loaded(Array2::default((1, 1))),
+                    })
+                },
+                |tile| Switch {
+                    number: tile.number,
+                    id: tile.switch_type.to_tw_switch_id(),
+                    flags: TileFlags::empty(),
+                    delay: tile.delay,
+                },
+            );
+        }
+
+        if let Some(tune) = &map.tune {
+            TwExport::process_physics_layer::<_, TuneLayer, _>(
+                &mut tw_map,
+                tune,
+                || {
+                    Layer::Tune(TuneLayer {
+                        tiles: CompressedData::Loaded(Array2::default((1, 1))),
+                    })
+                },
+                |tile| Tune {
+                    number: tile.number,
+                    id: TuneTile::TW_TUNE_ID,
+                },
+            );
+        }
+
+        // add optional decoration, after all tile layers are processed as this shifts groups
+        if let Some(decoration) = &map.decoration {
+            TwExport::process_decoration(&mut tw_map, decoration);
+        }
+
         // save map
         println!("exporting map to {:?}", &path);
         tw_map.save_file(path).expect("failed to write map file");
@@ -1,14 +1,33 @@
 use crate::{
-    config::{GenerationConfig, MapConfig},
+    config::{GenerationConfig, MapConfig, SelfAvoidanceStrategy},
+    flow_field::FlowField,
     generator,
-    kernel::Kernel,
-    map::{BlockType, Map, Overwrite},
+    kernel::{Kernel, KernelAnchor},
+    map::{BlockType, Map, Overwrite, Zone},
     position::{Position, ShiftDirection},
+    prefab::Prefab,
     random::Random,
 };
+use ndarray::{s, Array2};
+
+/// linear transition of the inner kernel size towards a sampled target size
+#[derive(Debug, Clone)]
+pub struct KernelTransition {
+    /// walker step at which the transition started
+    pub start_step: usize,
+
+    /// inner kernel size at the start of the transition
+    pub start_size: usize,
+
+    /// inner kernel size at the end of the transition
+    pub target_size: usize,
+
+    /// number of walker steps the transition takes
+    pub steps: usize,
+}
 
 // this walker is indeed very cute
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct CuteWalker {
     pub pos: Position,
     pub steps: usize,
@@ -23,10 +42,38 @@ pub struct CuteWalker {
 
     pub steps_since_platform: usize,
 
+    pub steps_since_prefab: usize,
+
     pub last_shift: Option<ShiftDirection>,
 
+    /// sub-tile anchor used for placing even sized kernels
+    pub anchor: KernelAnchor,
+
     /// counts how many steps the pulse constraints have been fulfilled
     pub pulse_counter: usize,
+
+    /// ongoing smooth inner kernel size transition
+    pub kernel_transition: Option<KernelTransition>,
+
+    /// counts consecutive steps in the same direction, reset whenever a speedup is placed
+    pub straight_steps: usize,
+
+    /// positions and directions of long straight runs, speedups are placed in post processing
+    pub speedup_candidates: Vec<(Position, ShiftDirection)>,
+
+    /// flow field towards the current goal, used for rating shifts if flow field steering is
+    /// enabled
+    pub flow_field: Option<FlowField>,
+
+    /// walker step at which each block was last covered by the outer kernel, usize::MAX if
+    /// never. Only tracked if self avoidance is enabled.
+    pub visited_steps: Option<Array2<usize>>,
+
+    /// walker positions since the last teleport or prefab, used for retreating
+    pub trace: Vec<Position>,
+
+    /// number of times the walker retreated towards the current goal
+    pub self_avoidance_retreats: usize,
 }
 
 impl CuteWalker {
@@ -46,8 +93,17 @@ impl CuteWalker {
             waypoints: map_config.waypoints.clone(),
             finished: false,
             steps_since_platform: 0,
+            steps_since_prefab: 0,
             last_shift: None,
+            anchor: KernelAnchor::default(),
             pulse_counter: 0,
+            kernel_transition: None,
+            straight_steps: 0,
+            speedup_candidates: Vec::new(),
+            flow_field: None,
+            visited_steps: None,
+            trace: Vec::new(),
+            self_avoidance_retreats: 0,
         }
     }
 
@@ -58,6 +114,8 @@ impl CuteWalker {
     }
 
     pub fn next_waypoint(&mut self) {
+        self.self_avoidance_retreats = 0;
+
         if let Some(next_goal) = self.waypoints.get(self.goal_index + 1) {
             self.goal_index += 1;
             self.goal = Some(next_goal.clone());
@@ -67,6 +125,53 @@ impl CuteWalker {
         }
     }
 
+    /// Returns the progress along the waypoints, which is 0 at the first and 1 at the last
+    /// waypoint. The progress on the current path is estimated using the distance to the goal.
+    pub fn progress(&self) -> f32 {
+        let path_lengths: Vec<f32> = self
+            .waypoints
+            .windows(2)
+            .map(|pair| (pair[0].distance_squared(&pair[1]) as f32).sqrt())
+            .collect();
+        let total_length: f32 = path_lengths.iter().sum();
+
+        let goal = match &self.goal {
+            Some(goal) if total_length > 0.0 => goal,
+            _ => return 1.0,
+        };
+
+        // walker is still heading towards the first waypoint
+        if self.goal_index == 0 {
+            return 0.0;
+        }
+
+        let path_length = path_lengths[self.goal_index - 1];
+        let finished_length: f32 = path_lengths[..self.goal_index - 1].iter().sum();
+        let remaining_length = (goal.distance_squared(&self.pos) as f32)
+            .sqrt()
+            .min(path_length);
+
+        (finished_length + path_length - remaining_length) / total_length
+    }
+
+    /// returns true if the current goal exists and is not the last waypoint
+    pub fn has_waypoint_after_goal(&self) -> bool {
+        self.goal.is_some() && self.goal_index + 1 < self.waypoints.len()
+    }
+
+    /// moves the walker directly onto its current goal and continues with the next waypoint.
+    /// Used for teleporter sections, so the path towards the goal is not carved.
+    pub fn teleport_to_goal(&mut self) {
+        if let Some(goal) = self.goal.clone() {
+            self.pos = goal;
+            self.last_shift = None;
+            self.pulse_counter = 0;
+            self.straight_steps = 0;
+            self.trace.clear();
+            self.next_waypoint();
+        }
+    }
+
     /// will try to place a platform at the walkers position.
     /// If force is true it will enforce a platform.
     pub fn check_platform(
@@ -92,11 +197,10 @@ impl CuteWalker {
         }
 
         // Case 3: min distance has been exceeded -> Try to place platform, but only if possible
-        let area_empty = map.check_area_all(
-            &walker_pos.shifted_by(-3, -3)?,
-            &walker_pos.shifted_by(3, 2)?,
-            &BlockType::Empty,
-        )?;
+        let area_top_left = walker_pos.shifted_by(-3, -3)?;
+        let area_bot_right = walker_pos.shifted_by(3, 2)?;
+        let area_empty = map.check_area_all(&area_top_left, &area_bot_right, &BlockType::Empty)?
+            && !map.check_zone_exists(&area_top_left, &area_bot_right, Zone::PROTECTED)?;
         if area_empty {
             map.set_area(
                 &walker_pos.shifted_by(-1, 0)?,
@@ -110,6 +214,42 @@ impl CuteWalker {
         Ok(())
     }
 
+    /// will try to place a prefab at the walkers position. If a prefab has been placed, the
+    /// walker continues at the exit of the prefab.
+    pub fn check_prefab(
+        &mut self,
+        map: &mut Map,
+        prefabs: &[Prefab],
+        config: &GenerationConfig,
+        rnd: &mut Random,
+    ) {
+        self.steps_since_prefab += 1;
+
+        if self.steps_since_prefab < config.prefab_min_distance
+            || !rnd.with_probability(config.prefab_prob)
+        {
+            return;
+        }
+
+        // prefab must be entered in walker direction
+        let name = rnd.sample_prefab_name();
+        let prefab = prefabs
+            .iter()
+            .find(|prefab| prefab.name == name && Some(prefab.entry_dir) == self.last_shift);
+
+        if let Some(prefab) = prefab {
+            let margin = self.outer_kernel.size;
+            if let Ok(exit_pos) = prefab.stamp(map, &self.pos, margin) {
+                self.pos = exit_pos;
+                self.last_shift = Some(prefab.exit_dir);
+                self.anchor.lean_towards(&prefab.exit_dir);
+                self.steps_since_prefab = 0;
+                self.straight_steps = 0;
+                self.trace.clear();
+            }
+        }
+    }
+
     pub fn probabilistic_step(
         &mut self,
         map: &mut Map,
@@ -122,25 +262,69 @@ impl CuteWalker {
 
         // sample next shift
         let goal = self.goal.as_ref().ok_or("Error: Goal is None")?;
-        let shifts = self.pos.get_rated_shifts(goal, map);
+        let shifts = match &self.flow_field {
+            Some(flow_field) => flow_field.get_rated_shifts(&self.pos, map),
+            None => self.pos.get_rated_shifts(goal, map),
+        };
 
         let mut current_shift = rnd.sample_shift(&shifts);
 
-        let same_dir = match self.last_shift {
-            Some(last_shift) => {
-                // Momentum: re-use last shift direction
-                if rnd.with_probability(config.momentum_prob) {
-                    current_shift = last_shift;
-                }
+        // Momentum: re-use last shift direction
+        if let Some(last_shift) = self.last_shift {
+            if rnd.with_probability(config.momentum_prob) {
+                current_shift = last_shift;
+            }
+        }
 
-                // check whether walker hasnt changed direction
-                current_shift == last_shift
+        // flow fields must never be left (e.g. towards the map border), so unreachable blocks
+        // are avoided, even if the shift was sampled or selected by momentum
+        if let Some(flow_field) = &self.flow_field {
+            if !flow_field.is_shift_reachable(&self.pos, &current_shift, map) {
+                current_shift = shifts[0];
             }
-            None => false,
-        };
+        }
+
+        // self avoidance: replace shifts that come too close to older parts of the path by the
+        // best rated shift that does not. Once all retreats towards the current goal have been
+        // used up, the walker ignores its path until the next waypoint.
+        if config.enable_self_avoidance && self.visited_steps.is_none() {
+            self.visited_steps = Some(Array2::from_elem((map.width, map.height), usize::MAX));
+        }
+
+        let retreats_exhausted = config.self_avoidance_strategy == SelfAvoidanceStrategy::Retreat
+            && self.self_avoidance_retreats >= config.self_avoidance_max_retreats;
+
+        if config.enable_self_avoidance
+            && !retreats_exhausted
+            && self.is_shift_avoided(&current_shift, map, config)
+        {
+            let free_shift = shifts.iter().find(|shift| {
+                !self.is_shift_avoided(shift, map, config)
+                    && self
+                        .flow_field
+                        .iter()
+                        .all(|flow_field| flow_field.is_shift_reachable(&self.pos, shift, map))
+            });
+
+            match (free_shift, config.self_avoidance_strategy) {
+                (Some(free_shift), _) => current_shift = *free_shift,
+                (None, SelfAvoidanceStrategy::Abort) => {
+                    return Err("Walker is blocked by its own path")
+                }
+                (None, SelfAvoidanceStrategy::Retreat) => {
+                    self.self_avoidance_retreats += 1;
+                    return self.retreat(config.self_avoidance_retreat_steps);
+                }
+                (None, SelfAvoidanceStrategy::ForceThrough) => {}
+            }
+        }
+
+        // check whether walker hasnt changed direction
+        let same_dir = self.last_shift == Some(current_shift);
 
         // apply selected shift
         self.pos.shift_in_direction(&current_shift, map)?;
+        self.anchor.lean_towards(&current_shift);
         self.steps += 1;
 
         // perform pulse if direction changed and config constraints allows it
@@ -162,13 +346,12 @@ impl CuteWalker {
             )?;
         } else {
             map.apply_kernel(self, &self.outer_kernel, BlockType::Freeze)?;
+            map.apply_kernel(self, &self.inner_kernel, BlockType::Empty)?;
 
-            let empty = if self.steps < config.fade_steps {
-                BlockType::EmptyReserved
-            } else {
-                BlockType::Empty
-            };
-            map.apply_kernel(self, &self.inner_kernel, empty)?;
+            // protect path while fading
+            if self.steps < config.fade_steps {
+                map.add_kernel_zone(self, &self.inner_kernel, Zone::RESERVED)?;
+            }
         };
 
         // apply kernels
@@ -179,8 +362,134 @@ impl CuteWalker {
             self.pulse_counter = 0;
         };
 
+        // remember long straight runs for speedups
+        if same_dir {
+            self.straight_steps += 1;
+        } else {
+            self.straight_steps = 0;
+        }
+
+        if config.enable_speedups && self.straight_steps >= config.speedup_straight_steps {
+            self.speedup_candidates
+                .push((self.pos.clone(), current_shift));
+            self.straight_steps = 0;
+        }
+
         self.last_shift = Some(current_shift.clone());
 
+        if config.enable_self_avoidance {
+            self.trace.push(self.pos.clone());
+            self.mark_visited();
+        }
+
+        Ok(())
+    }
+
+    /// returns the area that is covered by the outer kernel at the given position, enlarged
+    /// by margin and clamped to the map as (min_x, min_y, max_x, max_y)
+    fn outer_kernel_area(
+        &self,
+        pos: &Position,
+        margin: usize,
+        visited_steps: &Array2<usize>,
+    ) -> (usize, usize, usize, usize) {
+        let (width, height) = visited_steps.dim();
+        let radius = self.outer_kernel.size.div_ceil(2) + margin;
+        (
+            pos.x.saturating_sub(radius),
+            pos.y.saturating_sub(radius),
+            (pos.x + radius).min(width - 1),
+            (pos.y + radius).min(height - 1),
+        )
+    }
+
+    /// marks the area of the outer kernel at the current position as visited in this step
+    fn mark_visited(&mut self) {
+        if let Some(mut visited_steps) = self.visited_steps.take() {
+            let (min_x, min_y, max_x, max_y) = self.outer_kernel_area(&self.pos, 0, &visited_steps);
+            visited_steps
+                .slice_mut(s![min_x..=max_x, min_y..=max_y])
+                .fill(self.steps);
+            self.visited_steps = Some(visited_steps);
+        }
+    }
+
+    /// Returns the number of blocks closer than the self avoidance distance to the outer kernel
+    /// at the given position, that belong to parts of the path older than the min age. Blocks
+    /// around the goal are ignored, as the walker must be able to reach waypoints that are
+    /// close to its old path.
+    fn count_old_blocks(
+        &self,
+        pos: &Position,
+        visited_steps: &Array2<usize>,
+        config: &GenerationConfig,
+    ) -> usize {
+        let (min_x, min_y, max_x, max_y) =
+            self.outer_kernel_area(pos, config.self_avoidance_distance, visited_steps);
+        let min_step = self.steps.saturating_sub(config.self_avoidance_min_age);
+
+        let goal_radius = self.outer_kernel.size.div_ceil(2)
+            + config.self_avoidance_distance
+            + (config.waypoint_reached_dist as f32).sqrt() as usize;
+        let near_goal = |x: usize, y: usize| {
+            self.goal.as_ref().is_some_and(|goal| {
+                x.abs_diff(goal.x) <= goal_radius && y.abs_diff(goal.y) <= goal_radius
+            })
+        };
+
+        visited_steps
+            .slice(s![min_x..=max_x, min_y..=max_y])
+            .indexed_iter()
+            .filter(|((x, y), step)| **step < min_step && !near_goal(min_x + x, min_y + y))
+            .count()
+    }
+
+    /// Returns whether the outer kernel after shifting would come closer than the self
+    /// avoidance distance to parts of the path that are older than the self avoidance min age.
+    /// If the walker is already that close (e.g. due to a larger kernel), only shifts moving
+    /// away from the old path are allowed. Shifts towards the map border are always avoided.
+    fn is_shift_avoided(
+        &self,
+        shift: &ShiftDirection,
+        map: &Map,
+        config: &GenerationConfig,
+    ) -> bool {
+        let visited_steps = match &self.visited_steps {
+            Some(visited_steps) => visited_steps,
+            None => return false,
+        };
+
+        // avoiding the path must not drive the walker towards the map border
+        let mut shifted_pos = self.pos.clone();
+        if shifted_pos.shift_in_direction(shift, map).is_err()
+            || !map.square_in_bounds(&shifted_pos, config.max_kernel_size() / 2 + 2)
+        {
+            return true;
+        }
+
+        let shifted_count = self.count_old_blocks(&shifted_pos, visited_steps, config);
+        shifted_count > 0
+            && shifted_count >= self.count_old_blocks(&self.pos, visited_steps, config)
+    }
+
+    /// Moves the walker back along its trace without carving. The blocks on the way are marked
+    /// as visited again, so the abandoned part of the path is avoided once it is old enough.
+    fn retreat(&mut self, steps: usize) -> Result<(), &'static str> {
+        if self.trace.len() <= 1 {
+            return Err("Walker is blocked by its own path");
+        }
+
+        let target_index = self.trace.len().saturating_sub(steps + 1);
+        while self.trace.len() > target_index + 1 {
+            self.trace.pop();
+            self.pos = self.trace.last().unwrap().clone();
+            self.mark_visited();
+        }
+
+        self.last_shift = None;
+        self.pulse_counter = 0;
+        self.straight_steps = 0;
+
         Ok(())
     }
 
@@ -188,6 +497,17 @@ impl CuteWalker {
         println!("Cute walker was cuddled!");
     }
 
+    /// linearly interpolates a kernel size from start_size to end_size over the given steps
+    pub fn interpolate_kernel_size(
+        step: usize,
+        start_size: usize,
+        end_size: usize,
+        steps: usize,
+    ) -> f32 {
+        let slope = (end_size as f32 - start_size as f32) / steps as f32;
+        (step as f32) * slope + start_size as f32
+    }
+
     /// fades kernel size from max_size to min_size for fade_steps
     pub fn set_fade_kernel(
         &mut self,
@@ -196,13 +516,49 @@ impl CuteWalker {
         max_size: usize,
         fade_steps: usize,
     ) {
-        let slope = (min_size as f32 - max_size as f32) / fade_steps as f32;
-        let kernel_size_f = (step as f32) * slope + max_size as f32;
+        let kernel_size_f =
+            CuteWalker::interpolate_kernel_size(step, max_size, min_size, fade_steps);
         let kernel_size = kernel_size_f.floor() as usize;
         self.inner_kernel = Kernel::new(kernel_size, 0.0);
         self.outer_kernel = Kernel::new(kernel_size + 2, 0.0);
     }
 
+    /// starts a smooth transition from the current inner kernel size towards target_size, so
+    /// that the size changes by at most max_change every interval steps.
+    fn start_kernel_transition(&mut self, target_size: usize, max_change: usize, interval: usize) {
+        let start_size = self.inner_kernel.size;
+        let intervals = start_size.abs_diff(target_size).div_ceil(max_change);
+
+        self.kernel_transition = Some(KernelTransition {
+            start_step: self.steps,
+            start_size,
+            target_size,
+            steps: intervals * interval,
+        });
+    }
+
+    /// returns the inner kernel size of the current transition step, or None if there is no
+    /// ongoing transition. Finished transitions are removed.
+    fn step_kernel_transition(&mut self) -> Option<usize> {
+        let transition = self.kernel_transition.as_ref()?;
+        let step = self.steps - transition.start_step;
+
+        if step >= transition.steps {
+            let target_size = transition.target_size;
+            self.kernel_transition = None;
+            return Some(target_size);
+        }
+
+        // rounded to the nearest size, so that growing and shrinking are symmetric
+        let kernel_size_f = CuteWalker::interpolate_kernel_size(
+            step,
+            transition.start_size,
+            transition.target_size,
+            transition.steps,
+        );
+        Some(kernel_size_f.round() as usize)
+    }
+
     pub fn mutate_kernel(&mut self, config: &GenerationConfig, rnd: &mut Random) {
         let mut inner_size = self.inner_kernel.size;
         let mut inner_circ = self.inner_kernel.circularity;
@@ -239,6 +595,23 @@ impl CuteWalker {
             rnd.skip_n(2);
         }
 
+        // smooth kernel: sampled size only defines the target of a transition. Samples are
+        // ignored while a transition is ongoing, so the walker can actually reach its target.
+        if config.enable_smooth_kernel {
+            if self.kernel_transition.is_none() && inner_size != self.inner_kernel.size {
+                self.start_kernel_transition(
+                    inner_size,
+                    config.smooth_kernel_max_change,
+                    config.smooth_kernel_interval,
+                );
+            }
+
+            inner_size = self
+                .step_kernel_transition()
+                .unwrap_or(self.inner_kernel.size);
+            modified |= inner_size != self.inner_kernel.size;
+        }
+
         outer_size = inner_size + outer_margin;
 
         // constraint 1: small circles must be fully rect
@@ -258,3 +631,74 @@ impl CuteWalker {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_walker(kernel_size: usize) -> CuteWalker {
+        CuteWalker::new(
+            Position::new(50, 50),
+            Kernel::new(kernel_size, 0.0),
+            Kernel::new(kernel_size + 2, 0.0),
+            &MapConfig::default(),
+        )
+    }
+
+    #[test]
+    fn fade_kernel_sizes_are_floored() {
+        let mut walker = test_walker(5);
+        let sizes: Vec<usize> = (0..=8)
+            .map(|step| {
+                walker.set_fade_kernel(step, 1, 5, 8);
+                assert_eq!(walker.outer_kernel.size, walker.inner_kernel.size + 2);
+                walker.inner_kernel.size
+            })
+            .collect();
+        assert_eq!(sizes, vec![5, 4, 4, 3, 3, 2, 2, 1, 1]);
+    }
+
+    #[test]
+    fn kernel_transition_sizes_round_to_nearest() {
+        for (start_size, target_size, expected) in [
+            (1, 5, vec![1, 2, 2, 3, 3, 4, 4, 5, 5]),
+            (5, 1, vec![5, 5, 4, 4, 3, 3, 2, 2, 1]),
+        ] {
+            let mut walker = test_walker(start_size);
+            walker.start_kernel_transition(target_size, 1, 2);
+
+            let mut sizes = Vec::new();
+            while let Some(size) = walker.step_kernel_transition() {
+                sizes.push(size);
+                walker.steps += 1;
+            }
+
+            assert_eq!(sizes, expected);
+        }
+    }
+
+    #[test]
+    fn kernel_transition_limits_change_per_interval() {
+        let (max_change, interval) = (2, 5);
+
+        for (start_size, target_size) in [(1, 9), (9, 1), (3, 4), (6, 2)] {
+            let mut walker = test_walker(start_size);
+            walker.start_kernel_transition(target_size, max_change, interval);
+
+            let mut sizes = vec![start_size];
+            while let Some(size) = walker.step_kernel_transition() {
+                sizes.push(size);
+                walker.steps += 1;
+            }
+
+            assert_eq!(*sizes.last().unwrap(), target_size);
+            for window in sizes.windows(interval + 1) {
+                assert!(
+                    window[0].abs_diff(window[interval]) <= max_change,
+                    "size changed too fast: {:?}",
+                    sizes
+                );
+            }
+        }
+    }
+}